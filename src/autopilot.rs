@@ -0,0 +1,218 @@
+use crate::command::{Action, Command};
+use crate::navigator::{AimingNav, Navigator};
+use crate::submarine::Submarine;
+use std::cmp::Reverse;
+
+/// Decides how to change the aim given the tracking error the submarine would have after its next
+/// step if the aim were left alone. Positive errors mean the submarine would be too shallow.
+pub trait Controller {
+    fn aim_change(&mut self, error: i32) -> i32;
+}
+
+/// Changes the aim by a fixed `step` whenever the predicted error is larger than `deadband`.
+pub struct BangBang {
+    pub step: i32,
+    pub deadband: i32,
+}
+
+impl BangBang {
+    pub fn new(step: i32, deadband: i32) -> BangBang {
+        BangBang { step, deadband }
+    }
+}
+
+impl Controller for BangBang {
+    fn aim_change(&mut self, error: i32) -> i32 {
+        if i64::from(error.unsigned_abs()) <= i64::from(self.deadband) {
+            0
+        } else {
+            error.signum() * self.step
+        }
+    }
+}
+
+/// A PID controller whose output is limited to `max_change` per step.
+pub struct Pid {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+    pub max_change: i32,
+    integral: f64,
+    previous_error: Option<f64>,
+}
+
+impl Pid {
+    pub fn new(kp: f64, ki: f64, kd: f64, max_change: i32) -> Pid {
+        Pid {
+            kp,
+            ki,
+            kd,
+            max_change,
+            integral: 0.0,
+            previous_error: None,
+        }
+    }
+}
+
+impl Controller for Pid {
+    fn aim_change(&mut self, error: i32) -> i32 {
+        let error = f64::from(error);
+        self.integral += error;
+        let derivative = self.previous_error.map_or(0.0, |p| error - p);
+        self.previous_error = Some(error);
+        let output = self.kp * error + self.ki * self.integral + self.kd * derivative;
+        let limit = f64::from(self.max_change);
+        output.round().clamp(-limit, limit) as i32
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrackingStats {
+    pub mean_abs_error: f64,
+    pub rms_error: f64,
+    pub max_abs_error: i32,
+    /// The x position at which the largest error occurred.
+    pub worst_x: usize,
+}
+
+#[derive(Debug)]
+pub struct AutopilotRun {
+    pub commands: Vec<Command>,
+    pub submarine: Submarine,
+    pub stats: TrackingStats,
+}
+
+/// Turns a sonar sweep (seafloor depth at each x) into a target profile `margin` units above the
+/// seafloor, stopping at the ends of the range of depths.
+pub fn safety_profile(sweep: &[i32], margin: i32) -> Vec<i32> {
+    sweep
+        .iter()
+        .map(|depth| depth.saturating_sub(margin))
+        .collect()
+}
+
+/// Flies the submarine one unit forward at a time under aiming navigation, asking `controller`
+/// for an aim change before each step so that the depth at each x follows `profile[x]`. The
+/// submarine starts at the surface at x 0, so tracking is measured from x 1 onward.
+pub fn follow(profile: &[i32], controller: &mut impl Controller) -> Result<AutopilotRun, String> {
+    let nav = AimingNav::new();
+    let mut sub = Submarine::new();
+    let mut commands = vec![];
+    let mut errors = vec![];
+
+    for (x, target) in profile.iter().enumerate().skip(1) {
+        let predicted = sub
+            .overflow
+            .add(sub.depth, sub.aim)
+            .map_err(|e| format!("x {}: {}", x, e))?;
+        let change = controller.aim_change(target.saturating_sub(predicted));
+        let mut step = vec![];
+        if change > 0 {
+            step.push(Command::new(Action::DOWN, change));
+        } else if change < 0 {
+            step.push(Command::new(Action::UP, -change));
+        }
+        step.push(Command::new(Action::FORWARD, 1));
+        nav.act_on_each(&mut sub, step.iter())
+            .map_err(|e| format!("x {}: {}", x, e))?;
+        commands.extend(step);
+        errors.push(i64::from(sub.depth) - i64::from(*target));
+    }
+
+    Ok(AutopilotRun {
+        commands,
+        submarine: sub,
+        stats: tracking_stats(&errors),
+    })
+}
+
+fn tracking_stats(errors: &[i64]) -> TrackingStats {
+    if errors.is_empty() {
+        return TrackingStats::default();
+    }
+    let n = errors.len() as f64;
+    let (worst_idx, worst) = errors
+        .iter()
+        .enumerate()
+        .max_by_key(|(idx, e)| (e.abs(), Reverse(*idx)))
+        .unwrap();
+    TrackingStats {
+        mean_abs_error: errors.iter().map(|e| e.abs() as f64).sum::<f64>() / n,
+        rms_error: (errors.iter().map(|e| (*e as f64).powi(2)).sum::<f64>() / n).sqrt(),
+        max_abs_error: i32::try_from(worst.abs()).unwrap_or(i32::MAX),
+        worst_x: worst_idx + 1,
+    }
+}
+
+#[cfg(test)]
+const SAMPLE_SWEEP: [i32; 10] = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+#[test]
+fn test_perfect_tracking() {
+    let profile = safety_profile(&SAMPLE_SWEEP, 10);
+    assert_eq!(189, profile[0]);
+    let run = follow(&profile, &mut Pid::new(1.0, 0.0, 0.0, i32::MAX)).unwrap();
+    assert_eq!(0.0, run.stats.rms_error);
+    assert_eq!(0, run.stats.max_abs_error);
+    assert_eq!((9, 253), (run.submarine.x, run.submarine.depth));
+
+    // Replaying the generated commands gives the same result.
+    let mut sub = Submarine::new();
+    AimingNav::new()
+        .act_on_each(&mut sub, run.commands.iter())
+        .unwrap();
+    assert_eq!(run.submarine, sub);
+}
+
+#[test]
+fn test_bang_bang() {
+    let profile = vec![0, 2, 4, 6, 8, 10, 10, 10, 10];
+    let run = follow(&profile, &mut BangBang::new(1, 0)).unwrap();
+    assert_eq!(
+        Command::parse_each(vec![
+            "down 1",
+            "forward 1",
+            "down 1",
+            "forward 1",
+            "down 1",
+            "forward 1",
+            "up 1",
+            "forward 1",
+            "forward 1",
+            "up 1",
+            "forward 1",
+            "up 1",
+            "forward 1",
+            "up 1",
+            "forward 1",
+        ]),
+        Ok(run.commands)
+    );
+    assert_eq!(1, run.stats.max_abs_error);
+    assert_eq!(1, run.stats.worst_x);
+    assert_eq!(0.5, run.stats.mean_abs_error);
+}
+
+#[test]
+fn test_rate_limited_pid_follows_sonar_sweep() {
+    let sweep: Vec<i32> = include_str!("bin/inputs/problem1.txt")
+        .split_whitespace()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    let profile = safety_profile(&sweep, 20);
+    let run = follow(&profile, &mut Pid::new(0.8, 0.0, 0.1, 20)).unwrap();
+    assert_eq!(sweep.len() - 1, run.submarine.x as usize);
+    // The worst of it is diving from the surface at the start, after which it keeps close.
+    assert_eq!(1, run.stats.worst_x);
+    assert!(run.stats.mean_abs_error < 2.0, "{:?}", run.stats);
+}
+
+#[test]
+fn test_extreme_values() {
+    assert_eq!(-1, BangBang::new(1, 0).aim_change(i32::MIN));
+    assert_eq!(0, BangBang::new(1, i32::MAX).aim_change(i32::MIN + 1));
+    assert_eq!(vec![i32::MIN, 0], safety_profile(&[i32::MIN + 5, 10], 10));
+    let stats = tracking_stats(&[1 << 40, -(1 << 40)]);
+    assert_eq!(2f64.powi(40), stats.rms_error);
+    assert_eq!(i32::MAX, stats.max_abs_error);
+}
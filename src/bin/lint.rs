@@ -0,0 +1,123 @@
+use advent::lint::{Linter, Rule};
+use advent::registry::Registry;
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: lint LOG [--allow RULE]... [--max-value N] [--max-run N] [--nav NAME]
+
+Prints a warning for each likely mistake in the command log LOG. The rules are
+negative, zero, large, surfacing and no-forward. A comment such as
+\"# lint: allow zero\" allows rules for one command, and \"# lint: disable zero\"
+for the rest of the log.";
+
+fn number<T: std::str::FromStr>(value: Option<&String>, name: &str) -> Result<T, String> {
+    let value = value.ok_or(USAGE)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {} \"{}\"", name, value))
+}
+
+// The warnings found, one per line, prefixed with the path of the log.
+fn run(args: &[String]) -> Result<Vec<String>, String> {
+    let mut positional = vec![];
+    let mut linter = Linter::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        linter = match arg.as_str() {
+            "--allow" => linter.without(args.next().ok_or(USAGE)?.parse::<Rule>()?),
+            "--max-value" => linter.with_max_value(number(args.next(), "maximum value")?),
+            "--max-run" => linter.with_max_run(number(args.next(), "maximum run")?),
+            "--nav" => {
+                let name = args.next().ok_or(USAGE)?;
+                Registry::<i64>::builtin().get(name)?;
+                linter.with_navigator(name)
+            }
+            _ => {
+                positional.push(arg);
+                linter
+            }
+        };
+    }
+    let [log] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let contents = fs::read_to_string(log).map_err(|e| format!("Could not read {}: {}", log, e))?;
+    Ok(linter
+        .lint(contents.lines())
+        .map_err(|e| format!("{}: {}", log, e))?
+        .iter()
+        .map(|warning| format!("{}: {}", log, warning))
+        .collect())
+}
+
+#[test]
+fn test_lint_file() {
+    let log = env::temp_dir().join(format!("lint-{}.txt", process::id()));
+    fs::write(
+        &log,
+        "forward 5\ndown 0 # lint: allow zero\nup 0\nforward -2000\n",
+    )
+    .unwrap();
+    let path = log.display().to_string();
+    let args = |extra: &[&str]| {
+        let mut args = vec![path.clone()];
+        args.extend(extra.iter().map(|s| s.to_string()));
+        args
+    };
+
+    assert_eq!(
+        Ok(vec![
+            format!("{}: Line 3: \"up 0\" has no effect [zero]", path),
+            format!(
+                "{}: Line 4: \"forward -2000\" has a negative value [negative]",
+                path
+            ),
+            format!(
+                "{}: Line 4: \"forward -2000\" is larger than 1000 [large]",
+                path
+            ),
+        ]),
+        run(&args(&[]))
+    );
+    assert_eq!(
+        Ok(vec![format!(
+            "{}: Line 3: \"up 0\" has no effect [zero]",
+            path
+        )]),
+        run(&args(&["--allow", "negative", "--max-value", "5000"]))
+    );
+    assert_eq!(
+        Err("Invalid maximum run \"x\"".to_string()),
+        run(&args(&["--max-run", "x"]))
+    );
+    assert_eq!(
+        Err("Unknown navigator \"sideways\"".to_string()),
+        run(&args(&["--nav", "sideways"]))
+    );
+    assert_eq!(
+        Ok(3),
+        run(&args(&["--nav", "aiming"])).map(|warnings| warnings.len())
+    );
+    fs::remove_file(&log).unwrap();
+    assert_eq!(Err(USAGE.to_string()), run(&[]));
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(warnings) => {
+            for warning in &warnings {
+                println!("{}", warning);
+            }
+            if !warnings.is_empty() {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use advent::merge::{Log, Merger};
+use advent::navigator::StatefulNavigator;
+use advent::registry::Registry;
+use advent::submarine::Submarine;
+use std::env;
+#[cfg(test)]
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: merge-logs LOG... [--strict] [--nav NAME]
+
+Interleaves timestamped command logs into one log ordered by time, warning about
+commands at the same instant that pull in opposite directions. With --strict a
+conflict is an error. With --nav the final position is printed instead.";
+
+fn run(args: &[String]) -> Result<String, String> {
+    let mut merger = Merger::new();
+    let registry = Registry::builtin();
+    let mut nav = None;
+    let mut logs = 0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        merger = match arg.as_str() {
+            "--strict" => merger.strict(),
+            "--nav" => {
+                let name = args.next().ok_or(USAGE)?;
+                nav = Some((name, registry.get(name)?));
+                merger
+            }
+            path => {
+                logs += 1;
+                merger.with(Log::read(Path::new(path))?)
+            }
+        };
+    }
+    if logs == 0 {
+        return Err(USAGE.to_string());
+    }
+
+    let merged = merger.merge()?;
+    for conflict in &merged.conflicts {
+        eprintln!("Warning: {}", conflict);
+    }
+    match nav {
+        Some((name, mut nav)) => {
+            let mut sub = Submarine::new();
+            nav.drive_each(&mut sub, merged.commands())?;
+            Ok(format!(
+                "x: {} depth: {} aim: {} ({}, {} commands)",
+                sub.x,
+                sub.depth,
+                sub.aim,
+                name,
+                merged.entries.len()
+            ))
+        }
+        None => Ok(merged
+            .timed()
+            .iter()
+            .map(|timed| timed.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")),
+    }
+}
+
+#[test]
+fn test_merge_files() {
+    let dir = env::temp_dir();
+    let helm = dir.join(format!("helm-{}.log", process::id()));
+    let ballast = dir.join(format!("ballast-{}.log", process::id()));
+    fs::write(&helm, "0s forward 5\n2.5s forward 8\n").unwrap();
+    fs::write(&ballast, "1s down 5\n2.5s up 2\n").unwrap();
+    let args = |extra: &[&str]| {
+        let mut args = vec![helm.display().to_string(), ballast.display().to_string()];
+        args.extend(extra.iter().map(|s| s.to_string()));
+        args
+    };
+
+    assert_eq!(
+        Ok("0s forward 5\n1s down 5\n2.5s forward 8\n2.5s up 2".to_string()),
+        run(&args(&[]))
+    );
+    assert_eq!(
+        Ok("x: 13 depth: 40 aim: 3 (aiming, 4 commands)".to_string()),
+        run(&args(&["--nav", "aiming", "--strict"]))
+    );
+    assert_eq!(
+        Err("Unknown navigator \"sideways\"".to_string()),
+        run(&args(&["--nav", "sideways"]))
+    );
+
+    fs::write(&ballast, "1s down 5\n2.5s up 2\n2.5s down 1\n").unwrap();
+    assert_eq!(
+        Err(format!(
+            "At 2.5s {} \"up 2\" conflicts with {} \"down 1\"",
+            ballast.display(),
+            ballast.display()
+        )),
+        run(&args(&["--strict"]))
+    );
+    fs::remove_file(&helm).unwrap();
+    fs::remove_file(&ballast).unwrap();
+    assert_eq!(Err(USAGE.to_string()), run(&[]));
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use advent::checkpoint::Checkpoint;
+use advent::command::Command;
+use advent::registry::Registry;
+use advent::submarine::Submarine;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: mission LOG CHECKPOINT [--nav NAME] [--steps N]
+
+Runs the commands in LOG, resuming from CHECKPOINT if it exists, and saves the
+new checkpoint back to it. Checkpoints ending in .bin use the binary format.";
+
+fn load(path: &Path) -> Result<Checkpoint, String> {
+    let read_error = |e| format!("Could not read {}: {}", path.display(), e);
+    if path.extension().is_some_and(|e| e == "bin") {
+        Checkpoint::from_bytes(&fs::read(path).map_err(read_error)?)
+    } else {
+        fs::read_to_string(path).map_err(read_error)?.parse()
+    }
+}
+
+fn save(path: &Path, checkpoint: &Checkpoint) -> Result<(), String> {
+    let contents = if path.extension().is_some_and(|e| e == "bin") {
+        checkpoint.to_bytes()
+    } else {
+        checkpoint.to_string().into_bytes()
+    };
+    fs::write(path, contents).map_err(|e| format!("Could not save {}: {}", path.display(), e))
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let mut positional = vec![];
+    let registry = Registry::builtin();
+    let mut nav: Option<&str> = None;
+    let mut steps = usize::MAX;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--nav" => {
+                let name = args.next().ok_or(USAGE)?;
+                registry.get(name)?;
+                nav = Some(name);
+            }
+            "--steps" => {
+                let value = args.next().ok_or(USAGE)?;
+                steps = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of steps \"{}\"", value))?;
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [log, checkpoint_path] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let contents = fs::read_to_string(log).map_err(|e| format!("Could not read {}: {}", log, e))?;
+    let commands = Command::parse_each(contents.lines())?;
+    let checkpoint_path = Path::new(checkpoint_path);
+    let mut checkpoint = if checkpoint_path.exists() {
+        let checkpoint = load(checkpoint_path)?;
+        if nav.is_some_and(|nav| nav != checkpoint.nav) {
+            return Err(format!(
+                "{} was saved with the {} navigator",
+                checkpoint_path.display(),
+                checkpoint.nav
+            ));
+        }
+        checkpoint
+    } else {
+        Checkpoint::new(nav.unwrap_or("part1"), Submarine::new())
+    };
+
+    let result = checkpoint.advance(&registry, &commands, steps);
+    // Save whatever progress was made, even if a command failed.
+    save(checkpoint_path, &checkpoint)?;
+    result?;
+    let sub = checkpoint.submarine;
+    Ok(format!(
+        "x: {} depth: {} aim: {} ({}, {} of {} commands)",
+        sub.x,
+        sub.depth,
+        sub.aim,
+        checkpoint.nav,
+        checkpoint.index,
+        commands.len()
+    ))
+}
+
+#[test]
+fn test_pause_and_resume() {
+    let dir = env::temp_dir();
+    let id = process::id();
+    let log = dir.join(format!("mission-{}.txt", id));
+    fs::write(
+        &log,
+        "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2\n",
+    )
+    .unwrap();
+    let args = |checkpoint: &Path, extra: &[&str]| {
+        let mut args = vec![log.display().to_string(), checkpoint.display().to_string()];
+        args.extend(extra.iter().map(|s| s.to_string()));
+        args
+    };
+
+    for name in [format!("mission-{}.chk", id), format!("mission-{}.bin", id)] {
+        let checkpoint = dir.join(name);
+        assert_eq!(
+            Ok("x: 13 depth: 40 aim: 5 (aiming, 3 of 6 commands)".to_string()),
+            run(&args(&checkpoint, &["--nav", "aiming", "--steps", "3"]))
+        );
+        assert_eq!(
+            Err(format!(
+                "{} was saved with the aiming navigator",
+                checkpoint.display()
+            )),
+            run(&args(&checkpoint, &["--nav", "part1"]))
+        );
+        assert_eq!(
+            Ok("x: 15 depth: 60 aim: 10 (aiming, 6 of 6 commands)".to_string()),
+            run(&args(&checkpoint, &[]))
+        );
+        assert_eq!(
+            Err("Unknown navigator \"sideways\"".to_string()),
+            run(&args(&checkpoint, &["--nav", "sideways"]))
+        );
+        fs::remove_file(&checkpoint).unwrap();
+    }
+    fs::remove_file(&log).unwrap();
+    assert_eq!(Err(USAGE.to_string()), run(&[]));
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use advent::command::Command;
-use advent::navigator::{AimingNav, Navigator, PartOneNav};
+use advent::navigator::StatefulNavigator;
+use advent::registry::Registry;
 use advent::submarine::Submarine;
+use std::env;
 
 const MY_INPUT: &str = include_str!("inputs/problem2.txt");
 
@@ -20,17 +22,26 @@ fn test_sample_input() {
     ])
     .unwrap();
 
+    let registry = Registry::builtin();
     {
         let mut sub = Submarine::new();
         assert_eq!(0, destination_product(&sub));
 
-        PartOneNav::default().act_on_each(&mut sub, commands.iter());
+        registry
+            .get("part1")
+            .unwrap()
+            .drive_each(&mut sub, commands.iter())
+            .unwrap();
         assert_eq!(150, destination_product(&sub));
     }
 
     {
         let mut sub = Submarine::new();
-        AimingNav::default().act_on_each(&mut sub, commands.iter());
+        registry
+            .get("aiming")
+            .unwrap()
+            .drive_each(&mut sub, commands.iter())
+            .unwrap();
         assert_eq!(15, sub.x);
         assert_eq!(60, sub.depth);
         assert_eq!(900, destination_product(&sub));
@@ -39,16 +50,31 @@ fn test_sample_input() {
 
 fn main() {
     let commands = Command::parse_each(MY_INPUT.lines()).unwrap();
+    let registry = Registry::builtin();
 
-    {
-        let mut sub = Submarine::new();
-        PartOneNav::default().act_on_each(&mut sub, commands.iter());
-        println!("Part 1 result: {}", destination_product(&sub));
+    // Navigators can be picked by name, otherwise both parts are solved.
+    let names: Vec<String> = env::args().skip(1).collect();
+    if !names.is_empty() {
+        for name in names {
+            let mut sub = Submarine::new();
+            match registry.get(&name) {
+                Ok(mut nav) => {
+                    nav.drive_each(&mut sub, commands.iter()).unwrap();
+                    println!("{} result: {}", name, destination_product(&sub));
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        return;
     }
 
-    {
+    for (part, name) in [(1, "part1"), (2, "aiming")] {
         let mut sub = Submarine::new();
-        AimingNav::default().act_on_each(&mut sub, commands.iter());
-        println!("Part 2 result: {}", destination_product(&sub));
+        registry
+            .get(name)
+            .unwrap()
+            .drive_each(&mut sub, commands.iter())
+            .unwrap();
+        println!("Part {} result: {}", part, destination_product(&sub));
     }
 }
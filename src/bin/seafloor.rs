@@ -0,0 +1,79 @@
+use advent::collision;
+use advent::command::Command;
+use advent::registry::Registry;
+
+const SONAR_SWEEP: &str = include_str!("inputs/problem1.txt");
+const COURSE: &str = include_str!("inputs/problem2.txt");
+
+fn parse_sweep(sweep: &str) -> Vec<i32> {
+    sweep
+        .split_whitespace()
+        .map(|s| s.parse::<i32>().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_sample_input() {
+    let seafloor = [10; 16];
+    let commands = Command::parse_each(vec![
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+    ])
+    .unwrap();
+
+    let registry = Registry::builtin();
+    let report =
+        collision::check(&mut registry.get("part1").unwrap(), &seafloor, &commands).unwrap();
+    assert!(report.collisions.is_empty());
+    assert_eq!(
+        (Some(0), Some(13)),
+        (report.min_clearance, report.min_clearance_x)
+    );
+
+    let report =
+        collision::check(&mut registry.get("aiming").unwrap(), &seafloor, &commands).unwrap();
+    assert_eq!(
+        // Changing aim doesn't move the submarine, but it is still below the seafloor.
+        vec![(2, 13, 40), (3, 13, 40), (4, 13, 40), (5, 15, 60)],
+        report
+            .collisions
+            .iter()
+            .map(|c| (c.index, c.x, c.depth))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        (Some(-50), Some(15)),
+        (report.min_clearance, report.min_clearance_x)
+    );
+}
+
+fn main() {
+    let seafloor = parse_sweep(SONAR_SWEEP);
+    let commands = Command::parse_each(COURSE.lines()).unwrap();
+
+    let registry = Registry::builtin();
+    for nav in registry.names() {
+        let report =
+            collision::check(&mut registry.get(nav).unwrap(), &seafloor, &commands).unwrap();
+        println!(
+            "{}: {} of {} commands go below the seafloor",
+            nav,
+            report.collisions.len(),
+            commands.len()
+        );
+        if let Some(first) = report.collisions.first() {
+            println!(
+                "  First at command {} \"{}\": depth {} at x {} where the seafloor is {}",
+                first.index, first.command, first.depth, first.x, first.floor
+            );
+        }
+        match (report.min_clearance, report.min_clearance_x) {
+            (Some(clearance), Some(x)) => println!("  Minimum clearance {} at x {}", clearance, x),
+            _ => println!("  Never over the mapped seafloor"),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use advent::command::Command;
+use advent::journal::Journal;
+use advent::navigator::StatefulNavigator;
+use advent::registry::Registry;
+use advent::submarine::Submarine;
+use advent::units::Unit;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const HELP: &str = "Commands:
+  forward N | up N | down N  Pilot the submarine, optionally giving N a unit (1250ft)
+  undo                       Take back the last command
+  redo                       Reapply the last undone command
+  reset                      Return to the surface and forget all commands
+  nav NAME                   Switch navigator (part1, aiming) and replay the session
+  units m|ft|fathom          Show positions in the given unit
+  save PATH                  Write the session's commands to PATH
+  state                      Show the submarine's state
+  help                       Show this message
+  quit                       Leave the REPL";
+
+struct Session {
+    registry: Registry,
+    nav: String,
+    journal: Journal<Box<dyn StatefulNavigator>>,
+    unit: Option<Unit>,
+}
+
+impl Session {
+    fn new(nav: &str) -> Result<Session, String> {
+        let registry = Registry::builtin();
+        let journal = Journal::new(registry.get(nav)?, Submarine::new());
+        Ok(Session {
+            registry,
+            nav: nav.to_string(),
+            journal,
+            unit: None,
+        })
+    }
+
+    fn state(&self) -> String {
+        let sub = self.journal.submarine();
+        let length = |metres: i32| match self.unit {
+            Some(unit) => unit.format(f64::from(metres)),
+            None => metres.to_string(),
+        };
+        format!(
+            "x: {} depth: {} aim: {} ({}, {} commands)",
+            length(sub.x),
+            length(sub.depth),
+            sub.aim,
+            self.nav,
+            self.journal.position()
+        )
+    }
+
+    fn handle(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (word, arg) = match line.split_once(char::is_whitespace) {
+            Some((word, arg)) => (word, arg.trim()),
+            None => (line, ""),
+        };
+        match word {
+            "" | "state" => {}
+            "help" => return Ok(HELP.to_string()),
+            "undo" => self.journal.undo(1)?,
+            "redo" => self.journal.redo(1)?,
+            "reset" => self.journal = Journal::new(self.registry.get(&self.nav)?, Submarine::new()),
+            "nav" => {
+                let mut journal = Journal::new(self.registry.get(arg)?, Submarine::new());
+                journal.apply_each(self.journal.commands())?;
+                self.journal = journal;
+                self.nav = arg.to_string();
+            }
+            "units" => self.unit = Some(arg.parse()?),
+            "save" => {
+                if arg.is_empty() {
+                    return Err("Usage: save PATH".to_string());
+                }
+                let mut contents = String::new();
+                for command in self.journal.commands() {
+                    contents.push_str(&format!("{}\n", command));
+                }
+                fs::write(arg, contents).map_err(|e| format!("Could not save {}: {}", arg, e))?;
+                return Ok(format!(
+                    "Saved {} commands to {}",
+                    self.journal.position(),
+                    arg
+                ));
+            }
+            _ => {
+                self.journal.apply(line.parse::<Command>()?)?;
+            }
+        }
+        Ok(self.state())
+    }
+}
+
+#[test]
+fn test_piloting() {
+    let mut session = Session::new("part1").unwrap();
+    assert_eq!(
+        Ok("x: 5 depth: 0 aim: 0 (part1, 1 commands)".to_string()),
+        session.handle("forward 5")
+    );
+    assert_eq!(
+        Ok("x: 5 depth: 3 aim: 0 (part1, 2 commands)".to_string()),
+        session.handle("down 3")
+    );
+    assert_eq!(
+        Err("Unknown action \"sideways\"".to_string()),
+        session.handle("sideways 3")
+    );
+    assert_eq!(
+        Ok("x: 5 depth: 0 aim: 0 (part1, 1 commands)".to_string()),
+        session.handle("undo")
+    );
+    assert_eq!(
+        Ok("x: 5 depth: 3 aim: 0 (part1, 2 commands)".to_string()),
+        session.handle("redo")
+    );
+    assert_eq!(
+        Ok("x: 0 depth: 0 aim: 0 (part1, 0 commands)".to_string()),
+        session.handle("reset")
+    );
+    assert_eq!(
+        Err("Cannot undo 1 commands, only 0 applied".to_string()),
+        session.handle("undo")
+    );
+}
+
+#[test]
+fn test_switch_navigator() {
+    let mut session = Session::new("part1").unwrap();
+    session.handle("down 2").unwrap();
+    session.handle("forward 3").unwrap();
+    assert_eq!(
+        Ok("x: 3 depth: 6 aim: 2 (aiming, 2 commands)".to_string()),
+        session.handle("nav aiming")
+    );
+    assert_eq!(
+        Err("Unknown navigator \"sideways\"".to_string()),
+        session.handle("nav sideways")
+    );
+    assert_eq!(
+        Ok("x: 3 depth: 2 aim: 0 (part1, 2 commands)".to_string()),
+        session.handle("nav part1")
+    );
+}
+
+#[test]
+fn test_units() {
+    let mut session = Session::new("part1").unwrap();
+    session.handle("forward 2500ft").unwrap();
+    session.handle("down 625fathom").unwrap();
+    assert_eq!(
+        Ok("x: 762 depth: 1143 aim: 0 (part1, 2 commands)".to_string()),
+        session.handle("state")
+    );
+    assert_eq!(
+        Ok("x: 2500ft depth: 3750ft aim: 0 (part1, 2 commands)".to_string()),
+        session.handle("units ft")
+    );
+    assert_eq!(
+        Err("10ft is not a whole number of metres".to_string()),
+        session.handle("forward 10ft")
+    );
+    assert_eq!(
+        Err("Unknown unit \"yd\", expected one of m, ft or fathom".to_string()),
+        session.handle("units yd")
+    );
+    assert_eq!(
+        Ok("x: 762m depth: 1143m aim: 0 (part1, 2 commands)".to_string()),
+        session.handle("units m")
+    );
+}
+
+#[test]
+fn test_save() {
+    let path = env::temp_dir().join(format!("sub-repl-{}.txt", process::id()));
+    let mut session = Session::new("part1").unwrap();
+    session.handle("forward 5").unwrap();
+    session.handle("up -1").unwrap();
+    session.handle(&format!("save {}", path.display())).unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        Command::parse_each(vec!["forward 5", "up -1"]),
+        Command::parse_each(contents.lines())
+    );
+}
+
+fn main() {
+    // The navigator to start with can be given by name.
+    let nav = env::args().nth(1).unwrap_or("part1".to_string());
+    let mut session = match Session::new(&nav) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let stdin = io::stdin();
+    print!("> ");
+    io::stdout().flush().unwrap();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        if line.trim() == "quit" {
+            break;
+        }
+        match session.handle(&line) {
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("Error: {}", e),
+        }
+        print!("> ");
+        io::stdout().flush().unwrap();
+    }
+}
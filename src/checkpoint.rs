@@ -0,0 +1,410 @@
+use crate::command::Command;
+use crate::registry::Registry;
+use crate::scalar::{Overflow, Scalar};
+use crate::submarine::Submarine;
+use crate::varint;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+const TEXT_HEADER: &str = "checkpoint v1";
+const BINARY_MAGIC: &[u8] = b"SUB\x02";
+
+/// Everything needed to pick a mission back up: the name of the navigator in the registry, the
+/// submarine and how far through the command log it has got. Values are stored as 64 bit
+/// integers, so submarines of any scalar up to `i64` can be saved.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint<T = i32> {
+    pub nav: String,
+    pub submarine: Submarine<T>,
+    /// The index of the next command to apply.
+    pub index: usize,
+}
+
+impl<T: Scalar + Into<i64> + TryFrom<i64> + 'static> Checkpoint<T> {
+    pub fn new(nav: &str, submarine: Submarine<T>) -> Checkpoint<T> {
+        Checkpoint {
+            nav: nav.to_string(),
+            submarine,
+            index: 0,
+        }
+    }
+
+    /// Applies up to `count` more commands from `commands`, driven by the checkpoint's navigator
+    /// from `registry`. If a command fails the checkpoint is left just before it. Only the
+    /// submarine is saved, so a stateful navigator is first caught up by replaying the commands
+    /// already applied on a scratch submarine at the origin with the same overflow mode.
+    pub fn advance(
+        &mut self,
+        registry: &Registry<T>,
+        commands: &[Command],
+        count: usize,
+    ) -> Result<(), String> {
+        let mut nav = registry.get(&self.nav)?;
+        if self.index > commands.len() {
+            return Err(format!(
+                "Checkpoint is at command {} but the log only has {}",
+                self.index,
+                commands.len()
+            ));
+        }
+        if nav.state().is_some() {
+            let mut scratch = Submarine::with_overflow(self.submarine.overflow);
+            for (idx, command) in commands[..self.index].iter().enumerate() {
+                nav.drive(&mut scratch, command)
+                    .map_err(|e| format!("Command {}: {}", idx, e))?;
+            }
+        }
+        let end = self.index.saturating_add(count).min(commands.len());
+        while self.index < end {
+            nav.drive(&mut self.submarine, &commands[self.index])
+                .map_err(|e| format!("Command {}: {}", self.index, e))?;
+            self.index += 1;
+        }
+        Ok(())
+    }
+
+    /// Continues the mission from where the checkpoint left off to the end of `commands`.
+    pub fn resume(&mut self, registry: &Registry<T>, commands: &[Command]) -> Result<(), String> {
+        self.advance(registry, commands, usize::MAX)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = BINARY_MAGIC.to_vec();
+        varint::write_unsigned(&mut out, self.nav.len() as u64);
+        out.extend_from_slice(self.nav.as_bytes());
+        out.push(match self.submarine.overflow {
+            Overflow::CHECKED => 0,
+            Overflow::SATURATING => 1,
+            Overflow::WRAPPING => 2,
+        });
+        varint::write_unsigned(&mut out, self.index as u64);
+        let sub = &self.submarine;
+        for value in [sub.x, sub.depth, sub.aim] {
+            varint::write_signed(&mut out, value.into());
+        }
+        match sub.fuel {
+            None => out.push(0),
+            Some(fuel) => {
+                out.push(1);
+                varint::write_signed(&mut out, fuel.into());
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Checkpoint<T>, String> {
+        let rest = bytes
+            .strip_prefix(BINARY_MAGIC)
+            .ok_or_else(|| "Not a binary checkpoint".to_string())?;
+        let mut reader = Reader { rest };
+        let nav = reader.text("navigator")?;
+        let overflow = match reader.byte("overflow mode")? {
+            0 => Overflow::CHECKED,
+            1 => Overflow::SATURATING,
+            2 => Overflow::WRAPPING,
+            tag => return Err(format!("Unknown overflow tag {}", tag)),
+        };
+        let index = reader.unsigned()?;
+
+        let mut submarine = Submarine::with_overflow(overflow);
+        submarine.x = scalar("x", reader.signed()?)?;
+        submarine.depth = scalar("depth", reader.signed()?)?;
+        submarine.aim = scalar("aim", reader.signed()?)?;
+        submarine.fuel = match reader.byte("fuel")? {
+            0 => None,
+            1 => Some(scalar("fuel", reader.signed()?)?),
+            tag => return Err(format!("Unknown fuel tag {}", tag)),
+        };
+        if !reader.rest.is_empty() {
+            return Err(format!("{} unexpected bytes at the end", reader.rest.len()));
+        }
+
+        Ok(Checkpoint {
+            nav,
+            submarine,
+            index: usize::try_from(index).map_err(|_| format!("Index {} is too large", index))?,
+        })
+    }
+}
+
+struct Reader<'a> {
+    rest: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn byte(&mut self, name: &str) -> Result<u8, String> {
+        let (first, rest) = self
+            .rest
+            .split_first()
+            .ok_or_else(|| format!("Missing {}", name))?;
+        self.rest = rest;
+        Ok(*first)
+    }
+
+    fn unsigned(&mut self) -> Result<u64, String> {
+        let (value, len) = varint::read_unsigned(self.rest)?;
+        self.rest = &self.rest[len..];
+        Ok(value)
+    }
+
+    fn signed(&mut self) -> Result<i64, String> {
+        let (value, len) = varint::read_signed(self.rest)?;
+        self.rest = &self.rest[len..];
+        Ok(value)
+    }
+
+    // A length prefixed UTF-8 string.
+    fn text(&mut self, name: &str) -> Result<String, String> {
+        let len = self.unsigned()?;
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= self.rest.len())
+            .ok_or_else(|| format!("Missing {}", name))?;
+        let (text, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        String::from_utf8(text.to_vec()).map_err(|_| format!("Invalid {}", name))
+    }
+}
+
+fn scalar<T: TryFrom<i64>>(name: &str, value: i64) -> Result<T, String> {
+    T::try_from(value).map_err(|_| format!("Value {} of \"{}\" is out of range", value, name))
+}
+
+/// The text form is a header line followed by one `name value` line per field. `fuel` is left
+/// out for submarines that don't track fuel.
+impl<T: Scalar + Into<i64>> fmt::Display for Checkpoint<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sub = &self.submarine;
+        writeln!(f, "{}", TEXT_HEADER)?;
+        writeln!(f, "nav {}", self.nav)?;
+        writeln!(f, "index {}", self.index)?;
+        writeln!(f, "overflow {}", sub.overflow)?;
+        writeln!(f, "x {}", sub.x)?;
+        writeln!(f, "depth {}", sub.depth)?;
+        writeln!(f, "aim {}", sub.aim)?;
+        if let Some(fuel) = sub.fuel {
+            writeln!(f, "fuel {}", fuel)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Scalar + Into<i64> + TryFrom<i64>> FromStr for Checkpoint<T> {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Checkpoint<T>, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        if lines.next() != Some(TEXT_HEADER) {
+            return Err("Not a text checkpoint".to_string());
+        }
+        let mut fields = HashMap::new();
+        for line in lines {
+            let (name, value) = line
+                .split_once(' ')
+                .ok_or_else(|| format!("Invalid line \"{}\"", line))?;
+            if !["nav", "index", "overflow", "x", "depth", "aim", "fuel"].contains(&name) {
+                return Err(format!("Unknown field \"{}\"", name));
+            }
+            if fields.insert(name, value.trim()).is_some() {
+                return Err(format!("Duplicate field \"{}\"", name));
+            }
+        }
+
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("Missing field \"{}\"", name))
+        };
+        let number = |name: &str| -> Result<i64, String> {
+            let value = field(name)?;
+            value
+                .parse()
+                .map_err(|_| format!("Invalid value for \"{}\": \"{}\"", name, value))
+        };
+        let value = |name: &str| scalar::<T>(name, number(name)?);
+
+        let mut submarine = Submarine::with_overflow(field("overflow")?.parse()?);
+        submarine.x = value("x")?;
+        submarine.depth = value("depth")?;
+        submarine.aim = value("aim")?;
+        if fields.contains_key("fuel") {
+            submarine.fuel = Some(value("fuel")?);
+        }
+        let index = number("index")?;
+        Ok(Checkpoint {
+            nav: field("nav")?.to_string(),
+            submarine,
+            index: usize::try_from(index)
+                .map_err(|_| format!("Invalid value for \"index\": \"{}\"", index))?,
+        })
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::StatefulNavigator;
+
+#[cfg(test)]
+fn sample_commands() -> Vec<Command> {
+    Command::parse_each(vec![
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+    ])
+    .unwrap()
+}
+
+#[test]
+fn test_text_format() {
+    let mut checkpoint = Checkpoint::new("aiming", Submarine::with_fuel(100));
+    checkpoint
+        .advance(&Registry::builtin(), &sample_commands(), 3)
+        .unwrap();
+    let text = checkpoint.to_string();
+    assert_eq!(
+        "checkpoint v1\nnav aiming\nindex 3\noverflow checked\nx 13\ndepth 40\naim 5\nfuel 100\n",
+        text
+    );
+    assert_eq!(Ok(checkpoint), text.parse());
+
+    let unfueled = Checkpoint::<i64>::new("part1", Submarine::default());
+    assert_eq!(Ok(unfueled.clone()), unfueled.to_string().parse());
+}
+
+#[test]
+fn test_invalid_text() {
+    let parse = |text: &str| text.parse::<Checkpoint>().map(|_| ());
+    assert_eq!(
+        Err("Not a text checkpoint".to_string()),
+        parse("nav aiming")
+    );
+    assert_eq!(
+        Err("Missing field \"x\"".to_string()),
+        parse("checkpoint v1\nnav aiming\nindex 0\noverflow checked\ndepth 0\naim 0")
+    );
+    assert_eq!(
+        Err("Duplicate field \"x\"".to_string()),
+        parse("checkpoint v1\nx 1\nx 2")
+    );
+    assert_eq!(
+        Err("Unknown field \"speed\"".to_string()),
+        parse("checkpoint v1\nspeed 1")
+    );
+    assert_eq!(
+        Err("Value 4294967296 of \"x\" is out of range".to_string()),
+        parse("checkpoint v1\nnav aiming\nindex 0\noverflow checked\nx 4294967296\ndepth 0\naim 0")
+    );
+    assert_eq!(
+        Err("Unknown overflow mode \"loose\"".to_string()),
+        parse("checkpoint v1\nnav aiming\nindex 0\noverflow loose\nx 0\ndepth 0\naim 0")
+    );
+}
+
+#[test]
+fn test_binary_format() {
+    let mut checkpoint =
+        Checkpoint::<i64>::new("part1", Submarine::with_overflow(Overflow::WRAPPING));
+    checkpoint.submarine.fuel = Some(-7);
+    checkpoint
+        .advance(&Registry::builtin(), &sample_commands(), 4)
+        .unwrap();
+    let bytes = checkpoint.to_bytes();
+    assert_eq!(
+        b"SUB\x02\x05part1\x02\x04\x1a\x04\x00\x01\x0d",
+        bytes.as_slice()
+    );
+    assert_eq!(Ok(checkpoint), Checkpoint::from_bytes(&bytes));
+    assert_eq!(
+        Err("Missing navigator".to_string()),
+        Checkpoint::<i64>::from_bytes(b"SUB\x02\x09part1")
+    );
+
+    assert_eq!(
+        Err("Missing fuel".to_string()),
+        Checkpoint::<i64>::from_bytes(&bytes[..bytes.len() - 2])
+    );
+    assert_eq!(
+        Err("Truncated varint".to_string()),
+        Checkpoint::<i64>::from_bytes(&bytes[..13])
+    );
+    assert_eq!(
+        Err("Not a binary checkpoint".to_string()),
+        Checkpoint::<i64>::from_bytes(b"checkpoint v1")
+    );
+    let mut extra = bytes.clone();
+    extra.push(0);
+    assert_eq!(
+        Err("1 unexpected bytes at the end".to_string()),
+        Checkpoint::<i64>::from_bytes(&extra)
+    );
+}
+
+#[test]
+fn test_resume() {
+    let commands = Command::parse_each(include_str!("bin/inputs/problem2.txt").lines()).unwrap();
+    let registry = Registry::builtin();
+    for nav in registry.names() {
+        let mut expected = Submarine::new();
+        registry
+            .get(nav)
+            .unwrap()
+            .drive_each(&mut expected, commands.iter())
+            .unwrap();
+
+        // Stop and start a few times, going through both formats.
+        let mut checkpoint = Checkpoint::new(nav, Submarine::new());
+        checkpoint.advance(&registry, &commands, 300).unwrap();
+        let mut checkpoint: Checkpoint = checkpoint.to_string().parse().unwrap();
+        checkpoint.advance(&registry, &commands, 450).unwrap();
+        let mut checkpoint = Checkpoint::from_bytes(&checkpoint.to_bytes()).unwrap();
+        assert_eq!(750, checkpoint.index);
+        checkpoint.resume(&registry, &commands).unwrap();
+        assert_eq!(commands.len(), checkpoint.index);
+        assert_eq!(expected, checkpoint.submarine);
+    }
+
+    let mut checkpoint = Checkpoint::new("part1", Submarine::new());
+    checkpoint.index = 7;
+    assert_eq!(
+        Err("Checkpoint is at command 7 but the log only has 6".to_string()),
+        checkpoint.resume(&registry, &sample_commands())
+    );
+    let mut checkpoint = Checkpoint::new("sideways", Submarine::new());
+    assert_eq!(
+        Err("Unknown navigator \"sideways\"".to_string()),
+        checkpoint.resume(&registry, &sample_commands())
+    );
+}
+
+#[test]
+fn test_stateful_navigator_resumes() {
+    use crate::momentum::Momentum;
+
+    let mut registry = Registry::new();
+    registry.register("coasting", || Momentum::new(1)).unwrap();
+    let commands = sample_commands();
+    let mut unsplit = Checkpoint::new("coasting", Submarine::new());
+    unsplit.resume(&registry, &commands).unwrap();
+
+    let mut split = Checkpoint::new("coasting", Submarine::new());
+    split.advance(&registry, &commands, 3).unwrap();
+    split.advance(&registry, &commands, 3).unwrap();
+    assert_eq!(unsplit, split);
+    assert_eq!((54, 10), (split.submarine.x, split.submarine.depth));
+}
+
+#[test]
+fn test_failed_command_keeps_checkpoint() {
+    let commands = Command::parse_each(vec!["down 2147483647", "down 1", "up 1"]).unwrap();
+    let mut checkpoint = Checkpoint::new("part1", Submarine::new());
+    assert_eq!(
+        Err("Command 1: Arithmetic overflow: 2147483647 + 1".to_string()),
+        checkpoint.resume(&Registry::builtin(), &commands)
+    );
+    assert_eq!(1, checkpoint.index);
+    assert_eq!(i32::MAX, checkpoint.submarine.depth);
+}
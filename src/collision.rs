@@ -0,0 +1,197 @@
+use crate::command::Command;
+use crate::navigator::StatefulNavigator;
+use crate::submarine::Submarine;
+
+/// A command that took the submarine below the seafloor, with the point where it was deepest
+/// beneath it.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Collision {
+    pub index: usize,
+    pub command: Command,
+    pub x: i64,
+    pub depth: i64,
+    pub floor: i64,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CollisionReport {
+    pub collisions: Vec<Collision>,
+    /// The smallest distance between the submarine and the seafloor over the whole run, negative
+    /// if it went below. `None` if the submarine never passed over the mapped area.
+    pub min_clearance: Option<i64>,
+    /// Where the minimum clearance occurred.
+    pub min_clearance_x: Option<i64>,
+}
+
+/// Follows the submarine along its trajectory over a sonar map, where `seafloor[x]` is the
+/// depth of the seafloor at x. Depth is interpolated linearly along each forward move, which is
+/// exact for both built in navigators. Positions off the map aren't checked.
+pub fn check<N: StatefulNavigator<i64>>(
+    nav: &mut N,
+    seafloor: &[i32],
+    commands: &[Command],
+) -> Result<CollisionReport, String> {
+    let floor_at = |x: i64| {
+        usize::try_from(x)
+            .ok()
+            .and_then(|x| seafloor.get(x))
+            .map(|d| i64::from(*d))
+    };
+    let mut report = CollisionReport {
+        collisions: vec![],
+        min_clearance: None,
+        min_clearance_x: None,
+    };
+    let mut observe = |x: i64, depth: i64, worst: &mut Option<(i64, i64, i64)>| {
+        let Some(floor) = floor_at(x) else {
+            return;
+        };
+        let clearance = floor - depth;
+        if report.min_clearance.is_none_or(|c| clearance < c) {
+            report.min_clearance = Some(clearance);
+            report.min_clearance_x = Some(x);
+        }
+        if clearance < 0 && worst.is_none_or(|(_, d, f)| clearance < f - d) {
+            *worst = Some((x, depth, floor));
+        }
+    };
+
+    let mut sub = Submarine::<i64>::default();
+    observe(sub.x, sub.depth, &mut None);
+    let mut collisions = vec![];
+    for (index, command) in commands.iter().enumerate() {
+        let before = sub;
+        nav.drive(&mut sub, command)
+            .map_err(|e| format!("Command {}: {}", index, e))?;
+
+        let mut worst = None;
+        let (x0, x1) = (before.x, sub.x);
+        if x0 == x1 {
+            observe(sub.x, sub.depth, &mut worst);
+        } else {
+            // Only the part of the move over the map is walked, however far the submarine goes.
+            let last = i64::try_from(seafloor.len()).unwrap_or(i64::MAX) - 1;
+            let (first, end) = if x1 > x0 {
+                (x0.saturating_add(1).max(0), x1.min(last))
+            } else {
+                (x0.saturating_sub(1).min(last), x1.max(0))
+            };
+            let (d0, d1) = (i128::from(before.depth), i128::from(sub.depth));
+            let (span, step) = (i128::from(x1) - i128::from(x0), (x1 - x0).signum());
+            let mut x = first;
+            while (x - end) * step <= 0 {
+                let depth = (d1 - d0)
+                    .checked_mul(i128::from(x) - i128::from(x0))
+                    .map(|travel| d0 + travel / span)
+                    .and_then(|depth| i64::try_from(depth).ok())
+                    .ok_or_else(|| {
+                        format!("Command {}: Depth at x {} is out of range", index, x)
+                    })?;
+                observe(x, depth, &mut worst);
+                x += step;
+            }
+        }
+        if let Some((x, depth, floor)) = worst {
+            collisions.push(Collision {
+                index,
+                command: *command,
+                x,
+                depth,
+                floor,
+            });
+        }
+    }
+    report.collisions = collisions;
+    Ok(report)
+}
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, PartOneNav};
+
+#[test]
+fn test_no_collisions() {
+    let seafloor = [10, 10, 12, 12];
+    let commands = Command::parse_each(vec!["down 5", "forward 3"]).unwrap();
+    assert_eq!(
+        Ok(CollisionReport {
+            collisions: vec![],
+            min_clearance: Some(5),
+            min_clearance_x: Some(0),
+        }),
+        check(&mut PartOneNav::new(), &seafloor, &commands)
+    );
+}
+
+#[test]
+fn test_collision_along_forward() {
+    // Under aiming navigation, forward 3 at aim 4 passes depths 4, 8 and 12.
+    let seafloor = [20, 10, 7, 15, 15];
+    let commands = Command::parse_each(vec!["down 4", "forward 3", "up 4", "forward 1"]).unwrap();
+    assert_eq!(
+        Ok(CollisionReport {
+            collisions: vec![Collision {
+                index: 1,
+                command: "forward 3".parse().unwrap(),
+                x: 2,
+                depth: 8,
+                floor: 7,
+            }],
+            min_clearance: Some(-1),
+            min_clearance_x: Some(2),
+        }),
+        check(&mut AimingNav::new(), &seafloor, &commands)
+    );
+}
+
+#[test]
+fn test_collision_diving_in_place() {
+    let seafloor = [10, 10];
+    let commands = Command::parse_each(vec!["down 12", "up 4", "forward 1", "down 3"]).unwrap();
+    let report = check(&mut PartOneNav::new(), &seafloor, &commands).unwrap();
+    assert_eq!(
+        vec![0, 3],
+        report
+            .collisions
+            .iter()
+            .map(|c| c.index)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Some(-2), report.min_clearance);
+}
+
+#[test]
+fn test_off_the_map() {
+    let commands = Command::parse_each(vec!["forward -2", "down 100"]).unwrap();
+    assert_eq!(
+        Ok(CollisionReport {
+            collisions: vec![],
+            min_clearance: Some(10),
+            min_clearance_x: Some(0),
+        }),
+        check(&mut PartOneNav::new(), &[10], &commands)
+    );
+}
+
+#[test]
+fn test_large_moves() {
+    // The depth change times the distance travelled doesn't fit in an i64.
+    let commands = Command::parse_each(vec!["down 2147483647", "forward 2147483647"]).unwrap();
+    let report = check(&mut AimingNav::new(), &[10; 5], &commands).unwrap();
+    assert_eq!(1, report.collisions.len());
+    assert_eq!(
+        (1, 4, 8589934588),
+        (
+            report.collisions[0].index,
+            report.collisions[0].x,
+            report.collisions[0].depth
+        )
+    );
+
+    // Only the mapped part of a long move is walked.
+    let commands = Command::parse_each(vec!["forward 2000000000", "forward -2000000000"]).unwrap();
+    let report = check(&mut PartOneNav::new(), &[10; 5], &commands).unwrap();
+    assert_eq!(
+        (Some(10), Some(0)),
+        (report.min_clearance, report.min_clearance_x)
+    );
+}
@@ -1,22 +1,98 @@
-#[derive(Debug, Eq, PartialEq)]
+use crate::units::Unit;
+use crate::varint;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Action {
     FORWARD,
     UP,
     DOWN,
 }
 
-#[derive(Debug, Eq, PartialEq)]
-pub struct Command {
+impl Action {
+    fn tag(&self) -> u8 {
+        match self {
+            Action::FORWARD => 0,
+            Action::UP => 1,
+            Action::DOWN => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Action, String> {
+        match tag {
+            0 => Ok(Action::FORWARD),
+            1 => Ok(Action::UP),
+            2 => Ok(Action::DOWN),
+            _ => Err(format!("Unknown action tag {}", tag)),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(action_str: &str) -> Result<Action, String> {
+        match action_str {
+            "up" => Ok(Action::UP),
+            "down" => Ok(Action::DOWN),
+            "forward" => Ok(Action::FORWARD),
+            _ => Err(format!("Unknown action \"{}\"", action_str)),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Action::FORWARD => "forward",
+            Action::UP => "up",
+            Action::DOWN => "down",
+        })
+    }
+}
+
+/// A number that commands can carry. Logs normally hold whole metres, but `f64` and
+/// `crate::fixed::Fixed` values allow fractions such as "forward 2.5".
+pub trait Value: Copy + fmt::Display {
+    fn parse_value(text: &str) -> Option<Self>;
+
+    /// Converts the value from `unit` to metres.
+    fn to_metres(self, unit: Unit) -> Result<Self, String>;
+}
+
+impl Value for i32 {
+    fn parse_value(text: &str) -> Option<i32> {
+        text.parse().ok()
+    }
+
+    fn to_metres(self, unit: Unit) -> Result<i32, String> {
+        unit.to_metres(self)
+    }
+}
+
+impl Value for f64 {
+    fn parse_value(text: &str) -> Option<f64> {
+        text.parse().ok().filter(|v: &f64| v.is_finite())
+    }
+
+    fn to_metres(self, unit: Unit) -> Result<f64, String> {
+        Ok(unit.to_metres_f64(self))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Command<V = i32> {
     pub action: Action,
-    pub value: i32,
+    pub value: V,
 }
 
-impl Command {
-    fn new(action: Action, value: i32) -> Command {
+impl<V: Value> Command<V> {
+    pub fn new(action: Action, value: V) -> Command<V> {
         Command { action, value }
     }
 
-    fn parse(line: &str) -> Result<Command, String> {
+    fn parse(line: &str) -> Result<Command<V>, String> {
         let items: Vec<&str> = line.split_whitespace().collect();
         if items.len() != 2 {
             return Err(format!("Invalid command: \"{}\"", line));
@@ -24,25 +100,86 @@ impl Command {
 
         let action_str = &items[0];
         let value_str = &items[1];
-        let value = value_str
-            .parse::<i32>()
-            .map_err(|_| format!("Invalid command value: \"{}\"", value_str))?;
+        // An optional unit follows the number, as in "6ft". Values are stored in metres.
+        let (number, unit) = value_str.split_at(
+            value_str
+                .find(|c: char| c.is_ascii_alphabetic())
+                .unwrap_or(value_str.len()),
+        );
+        let value = V::parse_value(number)
+            .ok_or_else(|| format!("Invalid command value: \"{}\"", value_str))?;
 
-        let action = match *action_str {
-            "up" => Ok(Action::UP),
-            "down" => Ok(Action::DOWN),
-            "forward" => Ok(Action::FORWARD),
-            _ => Err(format!("Unknown action \"{}\"", action_str)),
-        }?;
+        let action = action_str.parse::<Action>()?;
+        let value = if unit.is_empty() {
+            value
+        } else {
+            value.to_metres(unit.parse::<Unit>()?)?
+        };
 
         Ok(Command::new(action, value))
     }
+}
 
+// Parsing many commands and the binary format are only for whole metre logs, which keeps type
+// inference working for the common case. Fractional logs can be parsed line by line with `parse`.
+impl Command {
     pub fn parse_each<'a>(
         lines: impl IntoIterator<Item = &'a str>,
     ) -> Result<Vec<Command>, String> {
         lines.into_iter().map(Command::parse).collect()
     }
+
+    /// Appends the binary form of the command to `out`: a one byte action tag followed by the
+    /// value as a zigzag varint.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.action.tag());
+        varint::write_signed(out, self.value.into());
+    }
+
+    /// Decodes one command from the start of `bytes`, returning it and the number of bytes read.
+    pub fn decode(bytes: &[u8]) -> Result<(Command, usize), String> {
+        let (tag, rest) = bytes
+            .split_first()
+            .ok_or_else(|| "Missing action tag".to_string())?;
+        let action = Action::from_tag(*tag)?;
+        let (value, len) = varint::read_signed(rest)?;
+        let value =
+            i32::try_from(value).map_err(|_| format!("Command value {} is out of range", value))?;
+        Ok((Command::new(action, value), len + 1))
+    }
+
+    pub fn encode_each<'a>(commands: impl IntoIterator<Item = &'a Command>) -> Vec<u8> {
+        let mut out = vec![];
+        for command in commands {
+            command.encode(&mut out);
+        }
+        out
+    }
+
+    pub fn decode_each(mut bytes: &[u8]) -> Result<Vec<Command>, String> {
+        let mut commands = vec![];
+        while !bytes.is_empty() {
+            let (command, len) =
+                Command::decode(bytes).map_err(|e| format!("Command {}: {}", commands.len(), e))?;
+            commands.push(command);
+            bytes = &bytes[len..];
+        }
+        Ok(commands)
+    }
+}
+
+impl<V: Value> FromStr for Command<V> {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Command<V>, String> {
+        Command::parse(line)
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for Command<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.action, self.value)
+    }
 }
 
 #[test]
@@ -56,17 +193,58 @@ fn test_parse() {
 
     assert_eq!(
         Err("Unknown action \"backward\"".to_string()),
-        Command::parse("backward 1")
+        Command::<i32>::parse("backward 1")
     );
 
-    assert_eq!(Err("Invalid command: \"\"".to_string()), Command::parse(""));
+    assert_eq!(
+        Err("Invalid command: \"\"".to_string()),
+        Command::<i32>::parse("")
+    );
     assert_eq!(
         Err("Invalid command: \"up 1 down 2\"".to_string()),
-        Command::parse("up 1 down 2")
+        Command::<i32>::parse("up 1 down 2")
     );
     assert_eq!(
         Err("Invalid command value: \"a\"".to_string()),
-        Command::parse("up a")
+        Command::<i32>::parse("up a")
+    );
+}
+
+#[test]
+fn test_parse_units() {
+    assert_eq!(
+        Command::parse_each(vec!["forward 10", "down 381", "down 1143", "up 762"]),
+        Command::parse_each(vec![
+            "forward 10m",
+            "down 1250ft",
+            "down 625fathom",
+            "up 2500ft"
+        ])
+    );
+    // 1.8288m doesn't fit in a whole metre log, but does in a fractional one.
+    assert_eq!(
+        Err("6ft is not a whole number of metres".to_string()),
+        Command::<i32>::parse("down 6ft")
+    );
+    assert_eq!(
+        Ok(Command::new(Action::DOWN, 1.8288)),
+        "down 6ft".parse::<Command<f64>>()
+    );
+    assert_eq!(
+        Err("Unknown unit \"yd\", expected one of m, ft or fathom".to_string()),
+        Command::<i32>::parse("forward 3yd")
+    );
+    assert_eq!(
+        Err("Invalid command value: \"1.5ft\"".to_string()),
+        Command::<i32>::parse("forward 1.5ft")
+    );
+    assert_eq!(
+        Err("Invalid command value: \"ft\"".to_string()),
+        Command::<i32>::parse("forward ft")
+    );
+    assert_eq!(
+        Err("2147483125fathom is out of range".to_string()),
+        Command::<i32>::parse("down 2147483125fathom")
     );
 }
 
@@ -87,3 +265,86 @@ fn test_parse_each() {
         Command::parse_each(vec!("up 1", "backward 2"))
     );
 }
+
+#[cfg(test)]
+fn every_kind_of_command() -> Vec<Command> {
+    let mut commands = vec![];
+    for action in [Action::FORWARD, Action::UP, Action::DOWN] {
+        for value in [0, 1, -1, 63, -64, 64, 1000, -1000, i32::MAX, i32::MIN] {
+            commands.push(Command::new(action, value));
+        }
+    }
+    commands
+}
+
+#[test]
+fn test_display_round_trip() {
+    assert_eq!("forward 5", Command::new(Action::FORWARD, 5).to_string());
+    assert_eq!("up -3", Command::new(Action::UP, -3).to_string());
+    assert_eq!("down 0", Command::new(Action::DOWN, 0).to_string());
+
+    for command in every_kind_of_command() {
+        let text = command.to_string();
+        assert_eq!(Ok(command), text.parse::<Command>());
+        assert_eq!(text, text.parse::<Command>().unwrap().to_string());
+    }
+
+    assert_eq!(
+        Err("Unknown action \"sideways\"".to_string()),
+        "sideways 1".parse::<Command>()
+    );
+}
+
+#[test]
+fn test_binary_round_trip() {
+    assert_eq!(
+        vec![0, 10, 1, 5, 2, 0xd0, 0x0f],
+        Command::encode_each(
+            &Command::parse_each(vec!["forward 5", "up -3", "down 1000"]).unwrap()
+        )
+    );
+
+    let commands = every_kind_of_command();
+    let bytes = Command::encode_each(&commands);
+    assert_eq!(Ok(commands), Command::decode_each(&bytes));
+    assert_eq!(Ok(vec![]), Command::decode_each(&[]));
+}
+
+#[test]
+fn test_decode_errors() {
+    assert_eq!(
+        Err("Command 1: Unknown action tag 7".to_string()),
+        Command::decode_each(&[0, 2, 7, 2])
+    );
+    assert_eq!(
+        Err("Command 0: Truncated varint".to_string()),
+        Command::decode_each(&[0, 0x80])
+    );
+    assert_eq!(
+        Err("Command 0: Truncated varint".to_string()),
+        Command::decode_each(&[2])
+    );
+    assert_eq!(
+        Err("Command 0: Command value 2147483648 is out of range".to_string()),
+        Command::decode_each(&[0, 0x80, 0x80, 0x80, 0x80, 0x10])
+    );
+}
+
+#[test]
+fn test_parse_fractions() {
+    assert_eq!(
+        Ok(Command::new(Action::FORWARD, 2.5)),
+        "forward 2.5".parse::<Command<f64>>()
+    );
+    assert_eq!(Ok(Command::new(Action::UP, 3.048)), "up 10ft".parse());
+    assert_eq!("down 0.25", Command::new(Action::DOWN, 0.25).to_string());
+    assert_eq!(
+        Err("Invalid command value: \"inf\"".to_string()),
+        "forward inf".parse::<Command<f64>>()
+    );
+    // Whole metre logs still reject fractions.
+    assert_eq!(
+        Err("Invalid command value: \"2.5\"".to_string()),
+        "forward 2.5".parse::<Command>()
+    );
+}
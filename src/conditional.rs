@@ -0,0 +1,419 @@
+use crate::command::Command;
+use crate::navigator::Navigator;
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+use std::fmt;
+use std::str::FromStr;
+
+// Conditional commands repeat a primitive command against the live state of the submarine:
+//
+//     down until depth 50
+//     forward 2 while depth < 100
+//     up 5 until aim <= 0
+//
+// The value defaults to 1. A bare number after the field in an `until` means reaching or passing
+// it, going whichever way the field had to go from where it was when the step started, so a
+// command that steps over the target still stops. Conditions are checked before every
+// repetition, so a command whose condition already holds doesn't run at all.
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Field {
+    X,
+    DEPTH,
+    AIM,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Field, String> {
+        match name {
+            "x" => Ok(Field::X),
+            "depth" => Ok(Field::DEPTH),
+            "aim" => Ok(Field::AIM),
+            _ => Err(format!("Unknown field \"{}\"", name)),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Field::X => "x",
+            Field::DEPTH => "depth",
+            Field::AIM => "aim",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    LESS,
+    LESSEQUAL,
+    GREATER,
+    GREATEREQUAL,
+    EQUAL,
+    NOTEQUAL,
+    /// Reaching or passing the value, written as a bare value. Until a step starts there's no
+    /// telling which way that is, so on its own it holds only at the value.
+    REACHES,
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(symbol: &str) -> Result<Comparison, String> {
+        match symbol {
+            "<" => Ok(Comparison::LESS),
+            "<=" => Ok(Comparison::LESSEQUAL),
+            ">" => Ok(Comparison::GREATER),
+            ">=" => Ok(Comparison::GREATEREQUAL),
+            "==" => Ok(Comparison::EQUAL),
+            "!=" => Ok(Comparison::NOTEQUAL),
+            _ => Err(format!("Unknown comparison \"{}\"", symbol)),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::LESS => "<",
+            Comparison::LESSEQUAL => "<=",
+            Comparison::GREATER => ">",
+            Comparison::GREATEREQUAL => ">=",
+            Comparison::EQUAL => "==",
+            Comparison::NOTEQUAL => "!=",
+            Comparison::REACHES => "",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Condition {
+    pub field: Field,
+    pub comparison: Comparison,
+    pub value: i32,
+}
+
+impl Condition {
+    pub fn holds<T: Scalar>(&self, submarine: &Submarine<T>) -> bool {
+        let actual = match self.field {
+            Field::X => submarine.x,
+            Field::DEPTH => submarine.depth,
+            Field::AIM => submarine.aim,
+        };
+        let value = T::from(self.value);
+        match self.comparison {
+            Comparison::LESS => actual < value,
+            Comparison::LESSEQUAL => actual <= value,
+            Comparison::GREATER => actual > value,
+            Comparison::GREATEREQUAL => actual >= value,
+            Comparison::EQUAL | Comparison::REACHES => actual == value,
+            Comparison::NOTEQUAL => actual != value,
+        }
+    }
+
+    /// The condition for a step starting from `submarine`, with reaching the value turned into
+    /// the comparison for the way the field has to go.
+    fn starting_at<T: Scalar>(&self, submarine: &Submarine<T>) -> Condition {
+        let comparison = match self.comparison {
+            Comparison::REACHES if self.holds(submarine) => Comparison::EQUAL,
+            Comparison::REACHES if self.with(Comparison::LESS).holds(submarine) => {
+                Comparison::GREATEREQUAL
+            }
+            Comparison::REACHES => Comparison::LESSEQUAL,
+            comparison => comparison,
+        };
+        self.with(comparison)
+    }
+
+    fn with(&self, comparison: Comparison) -> Condition {
+        Condition {
+            comparison,
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.comparison {
+            Comparison::REACHES => write!(f, "{} {}", self.field, self.value),
+            comparison => write!(f, "{} {} {}", self.field, comparison, self.value),
+        }
+    }
+}
+
+/// One line of a conditional program.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Step {
+    COMMAND(Command),
+    /// Repeats the command until the condition holds. A bare value in the condition means
+    /// `Comparison::REACHES`.
+    UNTIL(Command, Condition),
+    /// Repeats the command as long as the condition holds.
+    WHILE(Command, Condition),
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Step, String> {
+        let items: Vec<&str> = line.split_whitespace().collect();
+        let Some(keyword) = items.iter().position(|w| *w == "until" || *w == "while") else {
+            return Ok(Step::COMMAND(line.parse()?));
+        };
+
+        let command = match items[..keyword] {
+            [action] => Command::new(action.parse()?, 1),
+            [action, value] => format!("{} {}", action, value).parse()?,
+            _ => return Err(format!("Invalid command: \"{}\"", line)),
+        };
+        let bare = match items[keyword] {
+            "until" => Comparison::REACHES,
+            _ => Comparison::EQUAL,
+        };
+        let (field, comparison, value) = match items[keyword + 1..] {
+            [field, value] => (field, bare, value),
+            [field, comparison, value] => (field, comparison.parse()?, value),
+            _ => return Err(format!("Invalid condition: \"{}\"", line)),
+        };
+        let condition = Condition {
+            field: field.parse()?,
+            comparison,
+            value: value
+                .parse()
+                .map_err(|_| format!("Invalid condition value: \"{}\"", value))?,
+        };
+        Ok(match items[keyword] {
+            "until" => Step::UNTIL(command, condition),
+            _ => Step::WHILE(command, condition),
+        })
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::COMMAND(command) => write!(f, "{}", command),
+            Step::UNTIL(command, condition) => write!(f, "{} until {}", command, condition),
+            Step::WHILE(command, condition) => write!(f, "{} while {}", command, condition),
+        }
+    }
+}
+
+/// A list of steps that expands into primitive commands as it runs. Each conditional step gives up
+/// after `max_iterations` repetitions, since a condition the command never changes would
+/// otherwise loop forever, and the repetitions it made are taken back.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Program {
+    pub steps: Vec<Step>,
+    max_iterations: usize,
+}
+
+impl Program {
+    pub fn new(steps: Vec<Step>) -> Program {
+        Program {
+            steps,
+            max_iterations: 10_000,
+        }
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Program {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn parse_each<'a>(lines: impl IntoIterator<Item = &'a str>) -> Result<Program, String> {
+        let steps = lines
+            .into_iter()
+            .enumerate()
+            .map(|(idx, line)| line.parse().map_err(|e| format!("Line {}: {}", idx + 1, e)))
+            .collect::<Result<Vec<Step>, String>>()?;
+        Ok(Program::new(steps))
+    }
+
+    /// Runs the program, returning the trace of primitive commands it carried out. On error the
+    /// submarine is left where the last successful command put it, or where the step started if
+    /// it ran out of repetitions.
+    pub fn run<T: Scalar, N: Navigator<T>>(
+        &self,
+        navigator: &N,
+        submarine: &mut Submarine<T>,
+    ) -> Result<Vec<Command>, String> {
+        let mut trace = vec![];
+        for (idx, step) in self.steps.iter().enumerate() {
+            self.run_step(step, navigator, submarine, &mut trace)
+                .map_err(|e| format!("Step {}: {}", idx, e))?;
+        }
+        Ok(trace)
+    }
+
+    fn run_step<T: Scalar, N: Navigator<T>>(
+        &self,
+        step: &Step,
+        navigator: &N,
+        submarine: &mut Submarine<T>,
+        trace: &mut Vec<Command>,
+    ) -> Result<(), String> {
+        let (command, condition, repeat_while) = match step {
+            Step::COMMAND(command) => {
+                navigator.act_on(submarine, command)?;
+                trace.push(*command);
+                return Ok(());
+            }
+            Step::UNTIL(command, condition) => (command, condition, false),
+            Step::WHILE(command, condition) => (command, condition, true),
+        };
+        let condition = condition.starting_at(submarine);
+        let (start, traced) = (*submarine, trace.len());
+        for _ in 0..self.max_iterations {
+            if condition.holds(submarine) != repeat_while {
+                return Ok(());
+            }
+            navigator.act_on(submarine, command)?;
+            trace.push(*command);
+        }
+        if condition.holds(submarine) != repeat_while {
+            return Ok(());
+        }
+        *submarine = start;
+        trace.truncate(traced);
+        Err(format!(
+            "\"{}\" did not finish within {} repetitions",
+            step, self.max_iterations
+        ))
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, PartOneNav};
+
+#[test]
+fn test_parse() {
+    let steps = Program::parse_each(vec![
+        "down until depth 50",
+        "forward 2 while depth < 100",
+        "up 1250ft until aim <= -3",
+        "forward 3",
+    ])
+    .unwrap()
+    .steps;
+    assert_eq!(
+        vec![
+            "down 1 until depth 50",
+            "forward 2 while depth < 100",
+            "up 381 until aim <= -3",
+            "forward 3",
+        ],
+        steps.iter().map(Step::to_string).collect::<Vec<_>>()
+    );
+    for step in steps {
+        assert_eq!(Ok(step), step.to_string().parse());
+    }
+
+    for (line, error) in [
+        ("down until", "Invalid condition: \"down until\""),
+        ("down until height 5", "Unknown field \"height\""),
+        ("down until depth ~ 5", "Unknown comparison \"~\""),
+        ("down until depth x", "Invalid condition value: \"x\""),
+        ("until depth 5", "Invalid command: \"until depth 5\""),
+        ("sink until depth 5", "Unknown action \"sink\""),
+    ] {
+        assert_eq!(Err(error.to_string()), line.parse::<Step>());
+    }
+    assert_eq!(
+        Some("Line 2: Unknown field \"height\"".to_string()),
+        Program::parse_each(vec!["down 1", "up until height 0"]).err()
+    );
+}
+
+#[test]
+fn test_run() {
+    let program = Program::parse_each(vec![
+        "down until depth 50",
+        "forward 10 while x < 25",
+        "up 20 until depth <= 0",
+    ])
+    .unwrap();
+    let mut sub = Submarine::new();
+    let trace = program.run(&PartOneNav::new(), &mut sub).unwrap();
+    assert_eq!(50 + 3 + 3, trace.len());
+    assert_eq!((30, -10), (sub.x, sub.depth));
+
+    // Replaying the trace reaches the same state.
+    let mut replayed = Submarine::new();
+    PartOneNav::new()
+        .act_on_each(&mut replayed, trace.iter())
+        .unwrap();
+    assert_eq!(sub, replayed);
+
+    let program = Program::parse_each(vec!["down 5", "forward while depth < 100"]).unwrap();
+    let mut sub = Submarine::<i64>::default();
+    let trace = program.run(&AimingNav::new(), &mut sub).unwrap();
+    assert_eq!(21, trace.len());
+    assert_eq!((20, 100), (sub.x, sub.depth));
+
+    // A bare target is reached or passed, in whichever direction it lies.
+    let program = Program::parse_each(vec![
+        "down 7 until depth 30",
+        "up 4 until depth 20",
+        "down 4 until depth 20",
+    ])
+    .unwrap();
+    let mut sub = Submarine::new();
+    let trace = program.run(&PartOneNav::new(), &mut sub).unwrap();
+    assert_eq!(5 + 4 + 1, trace.len());
+    assert_eq!(23, sub.depth);
+    // A bare value in a while still means ==.
+    assert_eq!(
+        Ok("forward 1 while depth == 50".to_string()),
+        "forward while depth 50"
+            .parse::<Step>()
+            .map(|s| s.to_string())
+    );
+}
+
+#[test]
+fn test_loop_guard() {
+    // Going forward never changes the depth without aiming.
+    let program = Program::parse_each(vec!["down 5", "forward while depth < 100"])
+        .unwrap()
+        .with_max_iterations(100);
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err(
+            "Step 1: \"forward 1 while depth < 100\" did not finish within 100 repetitions"
+                .to_string()
+        ),
+        program.run(&PartOneNav::new(), &mut sub)
+    );
+    // The repetitions are taken back, leaving the submarine where the step started.
+    assert_eq!((0, 5), (sub.x, sub.depth));
+
+    // Overshooting an exact target never stops either, but reaching it on the last repetition is
+    // fine.
+    let mut sub = Submarine::new();
+    let overshoot = Program::parse_each(vec!["down 3 until depth == 10"])
+        .unwrap()
+        .with_max_iterations(4);
+    assert_eq!(
+        Err("Step 0: \"down 3 until depth == 10\" did not finish within 4 repetitions".to_string()),
+        overshoot.run(&PartOneNav::new(), &mut sub)
+    );
+    assert_eq!(0, sub.depth);
+    let exact = Program::parse_each(vec!["down 2 until depth 8"])
+        .unwrap()
+        .with_max_iterations(4);
+    let mut sub = Submarine::new();
+    assert_eq!(4, exact.run(&PartOneNav::new(), &mut sub).unwrap().len());
+
+    // A bare target stops on the repetition that passes it.
+    let passing = Program::parse_each(vec!["down 3 until depth 10"])
+        .unwrap()
+        .with_max_iterations(4);
+    let mut sub = Submarine::new();
+    assert_eq!(4, passing.run(&PartOneNav::new(), &mut sub).unwrap().len());
+    assert_eq!(12, sub.depth);
+}
@@ -0,0 +1,353 @@
+use crate::command::{Action, Command};
+use crate::navigator::{NavigatorKind, StatefulNavigator};
+use crate::scalar::{Overflow, Scalar};
+use crate::submarine::Submarine;
+use std::ops::Range;
+use std::thread;
+
+/// What a run of commands does to a submarine, independent of where it started. Applied to a
+/// submarine at (x, depth, aim) it gives
+///
+/// ```text
+/// (x + self.x, depth + self.depth + aim * self.aimed, aim + self.aim)
+/// ```
+///
+/// where `aimed` is the distance travelled at the starting aim, which is `x` under aiming
+/// navigation and zero otherwise. Effects compose associatively with `then`, so a log can be
+/// reduced in any grouping. They are worked out in wrapping i128, which reduces exactly to the
+/// wrapping arithmetic of any narrower scalar.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Effect {
+    pub x: i128,
+    pub depth: i128,
+    pub aim: i128,
+    pub aimed: i128,
+}
+
+impl Effect {
+    /// The effect of doing nothing.
+    pub fn identity() -> Effect {
+        Effect::default()
+    }
+
+    fn of(nav: NavigatorKind, command: &Command) -> Effect {
+        let value = i128::from(command.value);
+        match (nav, command.action) {
+            (NavigatorKind::PARTONE, Action::UP) => Effect {
+                depth: -value,
+                ..Effect::identity()
+            },
+            (NavigatorKind::PARTONE, Action::DOWN) => Effect {
+                depth: value,
+                ..Effect::identity()
+            },
+            (NavigatorKind::PARTONE, Action::FORWARD) => Effect {
+                x: value,
+                ..Effect::identity()
+            },
+            (NavigatorKind::AIMING, Action::UP) => Effect {
+                aim: -value,
+                ..Effect::identity()
+            },
+            (NavigatorKind::AIMING, Action::DOWN) => Effect {
+                aim: value,
+                ..Effect::identity()
+            },
+            (NavigatorKind::AIMING, Action::FORWARD) => Effect {
+                x: value,
+                aimed: value,
+                ..Effect::identity()
+            },
+        }
+    }
+
+    /// The effect of `commands` under `nav`, which must be one of the built in navigators.
+    pub fn of_each<'a>(
+        nav: &dyn StatefulNavigator,
+        commands: impl IntoIterator<Item = &'a Command>,
+    ) -> Result<Effect, String> {
+        Ok(Effect::of_kind_each(kind_of(nav)?, commands))
+    }
+
+    fn of_kind_each<'a>(
+        nav: NavigatorKind,
+        commands: impl IntoIterator<Item = &'a Command>,
+    ) -> Effect {
+        commands
+            .into_iter()
+            .fold(Effect::identity(), |effect, command| {
+                effect.then(Effect::of(nav, command))
+            })
+    }
+
+    /// This effect followed by `next`. The arithmetic wraps, which keeps composition exact modulo
+    /// 2^128 and so exact for wrapping submarines of any width.
+    pub fn then(self, next: Effect) -> Effect {
+        Effect {
+            x: self.x.wrapping_add(next.x),
+            depth: self
+                .depth
+                .wrapping_add(next.depth)
+                .wrapping_add(self.aim.wrapping_mul(next.aimed)),
+            aim: self.aim.wrapping_add(next.aim),
+            aimed: self.aimed.wrapping_add(next.aimed),
+        }
+    }
+
+    /// Moves the submarine as if each of the commands had been applied in turn, which for a
+    /// wrapping submarine gives an identical result. A checked submarine could overflow part way
+    /// through a run and a saturating one depends on the order the commands are applied in, and
+    /// neither can be told from the effect alone, so only wrapping submarines are accepted.
+    pub fn apply<T: Scalar + Into<i128>>(
+        &self,
+        submarine: &mut Submarine<T>,
+    ) -> Result<(), String> {
+        if submarine.overflow != Overflow::WRAPPING {
+            return Err(format!(
+                "Effects can't be applied to {} submarines",
+                match submarine.overflow {
+                    Overflow::CHECKED => "checked",
+                    _ => "saturating",
+                }
+            ));
+        }
+        let (x, depth, aim): (i128, i128, i128) = (
+            submarine.x.into(),
+            submarine.depth.into(),
+            submarine.aim.into(),
+        );
+        submarine.x = wrap(x.wrapping_add(self.x));
+        submarine.depth = wrap(
+            depth
+                .wrapping_add(self.depth)
+                .wrapping_add(aim.wrapping_mul(self.aimed)),
+        );
+        submarine.aim = wrap(aim.wrapping_add(self.aim));
+        Ok(())
+    }
+}
+
+fn kind_of(nav: &dyn StatefulNavigator) -> Result<NavigatorKind, String> {
+    nav.kind()
+        .ok_or_else(|| "Only the built in navigators have effects".to_string())
+}
+
+// Reduces `value` modulo the width of `T`, as wrapping arithmetic in `T` would have, by building
+// it up sixteen bits at a time.
+fn wrap<T: Scalar>(value: i128) -> T {
+    let bits = value as u128;
+    (0..8).rev().fold(T::default(), |result, limb| {
+        let limb = ((bits >> (16 * limb)) & 0xffff) as i32;
+        result
+            .wrapping_mul(T::from(0x10000))
+            .wrapping_add(T::from(limb))
+    })
+}
+
+/// Reduces `commands` to a single effect on up to `threads` threads, each of which reduces a
+/// contiguous chunk of the log.
+pub fn reduce_parallel(
+    nav: &dyn StatefulNavigator,
+    commands: &[Command],
+    threads: usize,
+) -> Result<Effect, String> {
+    let nav = kind_of(nav)?;
+    if commands.is_empty() {
+        return Ok(Effect::identity());
+    }
+    let chunk_size = commands.len().div_ceil(threads.max(1));
+    Ok(thread::scope(|scope| {
+        let handles: Vec<_> = commands
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || Effect::of_kind_each(nav, chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold(Effect::identity(), Effect::then)
+    }))
+}
+
+/// A segment tree over the effects of a log, answering "what do commands `i..j` do" in
+/// O(log n).
+pub struct EffectTree {
+    len: usize,
+    // nodes[len..] are the commands, and each nodes[i] below that combines nodes[2i] and
+    // nodes[2i + 1].
+    nodes: Vec<Effect>,
+}
+
+impl EffectTree {
+    pub fn new(nav: &dyn StatefulNavigator, commands: &[Command]) -> Result<EffectTree, String> {
+        let nav = kind_of(nav)?;
+        let len = commands.len();
+        let mut nodes = vec![Effect::identity(); len];
+        nodes.extend(commands.iter().map(|c| Effect::of(nav, c)));
+        for i in (1..len).rev() {
+            nodes[i] = nodes[2 * i].then(nodes[2 * i + 1]);
+        }
+        Ok(EffectTree { len, nodes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The combined effect of the commands in `range`.
+    pub fn query(&self, range: Range<usize>) -> Result<Effect, String> {
+        if range.start > range.end || range.end > self.len {
+            return Err(format!(
+                "Invalid range {}..{} of {} commands",
+                range.start, range.end, self.len
+            ));
+        }
+        let (mut left, mut right) = (Effect::identity(), Effect::identity());
+        let (mut lo, mut hi) = (range.start + self.len, range.end + self.len);
+        while lo < hi {
+            if lo % 2 == 1 {
+                left = left.then(self.nodes[lo]);
+                lo += 1;
+            }
+            if hi % 2 == 1 {
+                hi -= 1;
+                right = self.nodes[hi].then(right);
+            }
+            lo /= 2;
+            hi /= 2;
+        }
+        Ok(left.then(right))
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::Navigator;
+
+#[cfg(test)]
+fn long_log() -> Vec<Command> {
+    Command::parse_each(include_str!("bin/inputs/problem2.txt").lines()).unwrap()
+}
+
+#[test]
+fn test_matches_act_on_each() {
+    let commands = long_log();
+    for nav in NavigatorKind::ALL {
+        let mut expected = Submarine::<i64>::with_overflow(Overflow::WRAPPING);
+        nav.act_on_each(&mut expected, commands.iter()).unwrap();
+        let mut actual = Submarine::<i64>::with_overflow(Overflow::WRAPPING);
+        Effect::of_each(&nav, &commands)
+            .unwrap()
+            .apply(&mut actual)
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn test_matches_wrapping_overflow() {
+    // Big enough to wrap many times over.
+    let commands: Vec<Command> = long_log()
+        .iter()
+        .map(|c| Command::new(c.action, c.value.wrapping_mul(123_456_789)))
+        .collect();
+    for nav in NavigatorKind::ALL {
+        let mut expected = Submarine::with_overflow(Overflow::WRAPPING);
+        expected.aim = 7;
+        let mut actual = expected;
+        nav.act_on_each(&mut expected, commands.iter()).unwrap();
+        reduce_parallel(&nav, &commands, 4)
+            .unwrap()
+            .apply(&mut actual)
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn test_only_wrapping() {
+    // Applied one at a time these overflow after "down 2147483647", but the effect of the whole
+    // run is back in range, so checked submarines can't be trusted to an effect.
+    let commands =
+        Command::parse_each(vec!["down 2147483647", "down 1", "up 1", "forward 1"]).unwrap();
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err("Command 1: Arithmetic overflow: 2147483647 + 1".to_string()),
+        NavigatorKind::AIMING.act_on_each(&mut Submarine::new(), commands.iter())
+    );
+    assert_eq!(
+        Err("Effects can't be applied to checked submarines".to_string()),
+        Effect::of_each(&NavigatorKind::AIMING, &commands)
+            .unwrap()
+            .apply(&mut sub)
+    );
+    assert_eq!(
+        Err("Effects can't be applied to saturating submarines".to_string()),
+        Effect::of_each(&NavigatorKind::PARTONE, &commands)
+            .unwrap()
+            .apply(&mut Submarine::<i32>::with_overflow(Overflow::SATURATING))
+    );
+    assert_eq!(Submarine::new(), sub);
+}
+
+#[test]
+fn test_parallel_reduction() {
+    let commands = long_log();
+    for nav in NavigatorKind::ALL {
+        let expected = Effect::of_each(&nav, &commands).unwrap();
+        for threads in [0, 1, 3, 8, commands.len() + 5] {
+            assert_eq!(expected, reduce_parallel(&nav, &commands, threads).unwrap());
+        }
+    }
+    assert_eq!(
+        Effect::identity(),
+        reduce_parallel(&NavigatorKind::AIMING, &[], 4).unwrap()
+    );
+}
+
+#[test]
+fn test_range_queries() {
+    let commands = long_log()[..50].to_vec();
+    for nav in NavigatorKind::ALL {
+        let tree = EffectTree::new(&nav, &commands).unwrap();
+        assert_eq!(50, tree.len());
+        for start in (0..=50).step_by(7) {
+            for end in start..=50 {
+                // Replaying the range from a submarine that's already been somewhere.
+                let mut expected = Submarine::<i64>::with_overflow(Overflow::WRAPPING);
+                nav.act_on_each(&mut expected, commands[..start].iter())
+                    .unwrap();
+                let mut actual = expected;
+                nav.act_on_each(&mut expected, commands[start..end].iter())
+                    .unwrap();
+                tree.query(start..end).unwrap().apply(&mut actual).unwrap();
+                assert_eq!(expected, actual, "{}..{}", start, end);
+            }
+        }
+    }
+    let tree = EffectTree::new(&NavigatorKind::AIMING, &commands).unwrap();
+    assert_eq!(
+        Err("Invalid range 10..51 of 50 commands".to_string()),
+        tree.query(10..51)
+    );
+    assert_eq!(Ok(Effect::identity()), tree.query(20..20));
+}
+
+#[test]
+fn test_other_navigators() {
+    use crate::momentum::Momentum;
+    use crate::navigator::AimingNav;
+
+    let commands = long_log();
+    assert_eq!(
+        Effect::of_each(&NavigatorKind::AIMING, &commands),
+        Effect::of_each(&AimingNav::new(), &commands)
+    );
+    assert_eq!(
+        Err("Only the built in navigators have effects".to_string()),
+        reduce_parallel(&Momentum::new(1), &commands, 4)
+    );
+    assert!(EffectTree::new(&Momentum::new(1), &commands).is_err());
+}
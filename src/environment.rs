@@ -0,0 +1,316 @@
+use crate::command::Command;
+use crate::navigator::Navigator;
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+
+/// Something in the water that pushes the submarine around. Each command is followed by a drift
+/// of (x, depth) on top of whatever the command itself did.
+pub trait Current<T: Scalar = i32> {
+    fn drift(&self, submarine: &Submarine<T>, command: &Command) -> (i32, i32);
+
+    /// A copy of whatever the current changes as it drifts, or `None` if it never changes.
+    fn state(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    /// Puts back a state taken earlier with `state`.
+    fn restore(&self, _state: &dyn Any) {}
+}
+
+/// The same push after every command.
+pub struct ConstantCurrent {
+    pub x: i32,
+    pub depth: i32,
+}
+
+impl ConstantCurrent {
+    pub fn new(x: i32, depth: i32) -> ConstantCurrent {
+        ConstantCurrent { x, depth }
+    }
+}
+
+impl<T: Scalar> Current<T> for ConstantCurrent {
+    fn drift(&self, _: &Submarine<T>, _: &Command) -> (i32, i32) {
+        (self.x, self.depth)
+    }
+}
+
+/// Currents that vary with depth. Each layer applies from its starting depth down to the start of
+/// the next layer. Above the first layer the water is still.
+pub struct DepthProfile {
+    // (starting depth, x, depth), sorted by starting depth.
+    layers: Vec<(i32, i32, i32)>,
+}
+
+impl DepthProfile {
+    pub fn new(mut layers: Vec<(i32, i32, i32)>) -> DepthProfile {
+        layers.sort_by_key(|(start, _, _)| *start);
+        DepthProfile { layers }
+    }
+}
+
+impl<T: Scalar> Current<T> for DepthProfile {
+    fn drift(&self, submarine: &Submarine<T>, _: &Command) -> (i32, i32) {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(start, _, _)| T::from(*start) <= submarine.depth)
+            .map_or((0, 0), |(_, x, depth)| (*x, *depth))
+    }
+}
+
+/// Random pushes of up to `strength` along each axis. The same seed always produces the same
+/// sequence of pushes.
+pub struct Turbulence {
+    strength: i32,
+    state: Cell<u64>,
+}
+
+impl Turbulence {
+    pub fn new(seed: u64, strength: i32) -> Turbulence {
+        Turbulence {
+            strength: strength.saturating_abs(),
+            // xorshift gets stuck at zero.
+            state: Cell::new(seed.max(1)),
+        }
+    }
+
+    fn next(&self) -> i32 {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state.set(x);
+        let span = 2 * u64::from(self.strength.unsigned_abs()) + 1;
+        ((x % span) as i64 - i64::from(self.strength)) as i32
+    }
+}
+
+impl<T: Scalar> Current<T> for Turbulence {
+    fn drift(&self, _: &Submarine<T>, _: &Command) -> (i32, i32) {
+        (self.next(), self.next())
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.state.get()))
+    }
+
+    fn restore(&self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<u64>() {
+            self.state.set(*state);
+        }
+    }
+}
+
+/// Any number of currents acting at once.
+pub struct Environment<T = i32> {
+    currents: Vec<Box<dyn Current<T>>>,
+}
+
+impl<T: Scalar> Environment<T> {
+    pub fn new() -> Environment<T> {
+        Environment { currents: vec![] }
+    }
+
+    pub fn with(mut self, current: impl Current<T> + 'static) -> Environment<T> {
+        self.currents.push(Box::new(current));
+        self
+    }
+}
+
+impl<T: Scalar> Default for Environment<T> {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
+impl<T: Scalar> Current<T> for Environment<T> {
+    fn drift(&self, submarine: &Submarine<T>, command: &Command) -> (i32, i32) {
+        self.currents.iter().fold((0, 0), |(x, depth), current| {
+            let (dx, ddepth) = current.drift(submarine, command);
+            (x.saturating_add(dx), depth.saturating_add(ddepth))
+        })
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        let states: Vec<_> = self.currents.iter().map(|c| c.state()).collect();
+        Some(Box::new(states))
+    }
+
+    fn restore(&self, state: &dyn Any) {
+        if let Some(states) = state.downcast_ref::<Vec<Option<Box<dyn Any>>>>() {
+            for (current, state) in self.currents.iter().zip(states) {
+                if let Some(state) = state {
+                    current.restore(state.as_ref());
+                }
+            }
+        }
+    }
+}
+
+/// Wraps a navigator so that the environment is consulted after every command.
+pub struct Drifting<N, C> {
+    navigator: N,
+    current: C,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DriftReport<T = i32> {
+    pub command: Command,
+    pub planned: Submarine<T>,
+    pub actual: Submarine<T>,
+}
+
+impl<T: Scalar> fmt::Display for DriftReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<12} planned x: {} depth: {}  actual x: {} depth: {}",
+            self.command.to_string(),
+            self.planned.x,
+            self.planned.depth,
+            self.actual.x,
+            self.actual.depth
+        )
+    }
+}
+
+impl<N, C> Drifting<N, C> {
+    pub fn new(navigator: N, current: C) -> Drifting<N, C> {
+        Drifting { navigator, current }
+    }
+
+    /// Runs `commands` both in still water and in the environment, reporting where the submarine
+    /// was planned to be and where it actually was after each command. The current is left as it
+    /// was, so a report doesn't change what the navigator does next.
+    pub fn report<T: Scalar>(
+        &self,
+        submarine: &Submarine<T>,
+        commands: &[Command],
+    ) -> Result<Vec<DriftReport<T>>, String>
+    where
+        N: Navigator<T>,
+        C: Current<T>,
+    {
+        let saved = self.current.state();
+        let mut planned = *submarine;
+        let mut actual = *submarine;
+        let report = commands
+            .iter()
+            .enumerate()
+            .map(|(idx, command)| {
+                self.navigator
+                    .act_on(&mut planned, command)
+                    .and_then(|_| self.act_on(&mut actual, command))
+                    .map_err(|e| format!("Command {}: {}", idx, e))?;
+                Ok(DriftReport {
+                    command: *command,
+                    planned,
+                    actual,
+                })
+            })
+            .collect();
+        if let Some(state) = saved {
+            self.current.restore(state.as_ref());
+        }
+        report
+    }
+}
+
+impl<T: Scalar, N: Navigator<T>, C: Current<T>> Navigator<T> for Drifting<N, C> {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command) -> Result<(), String> {
+        let mut next = *submarine;
+        self.navigator.act_on(&mut next, command)?;
+        let (x, depth) = self.current.drift(&next, command);
+        next.forward(T::from(x))?;
+        next.dive(T::from(depth))?;
+        *submarine = next;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::PartOneNav;
+
+#[test]
+fn test_constant_current() {
+    let commands = Command::parse_each(vec!["forward 5", "down 3"]).unwrap();
+    let mut sub = Submarine::new();
+    Drifting::new(PartOneNav::new(), ConstantCurrent::new(1, -1))
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!((7, 1), (sub.x, sub.depth));
+}
+
+#[test]
+fn test_depth_profile() {
+    let profile = DepthProfile::new(vec![(10, 2, 0), (0, 1, 0), (20, -1, 1)]);
+    let commands = Command::parse_each(vec!["forward 1", "down 10", "down 10", "up 25"]).unwrap();
+    let mut sub = Submarine::new();
+    let nav = Drifting::new(PartOneNav::new(), profile);
+    let mut xs = vec![];
+    for command in &commands {
+        nav.act_on(&mut sub, command).unwrap();
+        xs.push((sub.x, sub.depth));
+    }
+    assert_eq!(vec![(2, 0), (4, 10), (3, 21), (3, -4)], xs);
+}
+
+#[test]
+fn test_turbulence_is_seeded() {
+    let commands = Command::parse_each(vec!["forward 1"; 50]).unwrap();
+    let run = |seed| {
+        let mut sub = Submarine::new();
+        Drifting::new(PartOneNav::new(), Turbulence::new(seed, 2))
+            .act_on_each(&mut sub, commands.iter())
+            .unwrap();
+        (sub.x, sub.depth)
+    };
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+
+    let turbulence = Turbulence::new(7, 2);
+    for _ in 0..1000 {
+        assert!((-2..=2).contains(&turbulence.next()));
+    }
+}
+
+#[test]
+fn test_report() {
+    let environment = Environment::new()
+        .with(ConstantCurrent::new(1, 0))
+        .with(DepthProfile::new(vec![(5, 0, 1)]));
+    let commands = Command::parse_each(vec!["down 5", "forward 2"]).unwrap();
+    let report = Drifting::new(PartOneNav::new(), environment)
+        .report(&Submarine::new(), &commands)
+        .unwrap();
+    assert_eq!(
+        vec![((0, 5), (1, 6)), ((2, 5), (4, 7))],
+        report
+            .iter()
+            .map(|r| ((r.planned.x, r.planned.depth), (r.actual.x, r.actual.depth)))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        "forward 2    planned x: 2 depth: 5  actual x: 4 depth: 7",
+        report[1].to_string()
+    );
+}
+
+#[test]
+fn test_report_leaves_turbulence_alone() {
+    let commands = Command::parse_each(vec!["forward 1"; 10]).unwrap();
+    let environment = Environment::new()
+        .with(ConstantCurrent::new(1, 0))
+        .with(Turbulence::new(7, 3));
+    let nav = Drifting::new(PartOneNav::new(), environment);
+    let first = nav.report(&Submarine::new(), &commands).unwrap();
+    assert_eq!(first, nav.report(&Submarine::new(), &commands).unwrap());
+
+    let mut sub = Submarine::new();
+    nav.act_on_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!(first.last().unwrap().actual, sub);
+}
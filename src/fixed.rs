@@ -0,0 +1,187 @@
+use crate::command::Value;
+use crate::scalar::Scalar;
+use crate::units::Unit;
+use std::fmt;
+use std::str::FromStr;
+
+/// A fixed point number with three decimal places, which is millimetre precision for lengths in
+/// metres. Unlike `f64`, sums of decimal values such as 0.1 are exact.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    const SCALE: i64 = 1000;
+
+    pub fn from_thousandths(thousandths: i64) -> Fixed {
+        Fixed(thousandths)
+    }
+
+    pub fn thousandths(self) -> i64 {
+        self.0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Fixed::SCALE as f64
+    }
+
+    // The product in thousandths, rounding halves away from zero, before it's fitted to an i64.
+    fn product(self, rhs: Fixed) -> i128 {
+        let product = i128::from(self.0) * i128::from(rhs.0);
+        let scale = i128::from(Fixed::SCALE);
+        (product + product.signum() * scale / 2) / scale
+    }
+}
+
+impl From<i32> for Fixed {
+    fn from(value: i32) -> Fixed {
+        Fixed(i64::from(value) * Fixed::SCALE)
+    }
+}
+
+/// Rounds to the nearest thousandth, saturating at the ends of the range.
+impl From<f64> for Fixed {
+    fn from(value: f64) -> Fixed {
+        Fixed((value * Fixed::SCALE as f64).round() as i64)
+    }
+}
+
+impl Scalar for Fixed {
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Fixed)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Fixed)
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        i64::try_from(self.product(rhs)).ok().map(Fixed)
+    }
+
+    fn saturating_add(self, rhs: Self) -> Self {
+        Fixed(self.0.saturating_add(rhs.0))
+    }
+
+    fn saturating_sub(self, rhs: Self) -> Self {
+        Fixed(self.0.saturating_sub(rhs.0))
+    }
+
+    fn saturating_mul(self, rhs: Self) -> Self {
+        let product = self.product(rhs);
+        Fixed(product.clamp(i64::MIN.into(), i64::MAX.into()) as i64)
+    }
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        Fixed(self.0.wrapping_add(rhs.0))
+    }
+
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        Fixed(self.0.wrapping_sub(rhs.0))
+    }
+
+    fn wrapping_mul(self, rhs: Self) -> Self {
+        Fixed(self.product(rhs) as i64)
+    }
+}
+
+impl Value for Fixed {
+    fn parse_value(text: &str) -> Option<Fixed> {
+        text.parse().ok()
+    }
+
+    fn to_metres(self, unit: Unit) -> Result<Fixed, String> {
+        let scaled = i128::from(self.0) * i128::from(unit.length());
+        let metres = (scaled + scaled.signum() * 5_000) / 10_000;
+        i64::try_from(metres)
+            .map(Fixed)
+            .map_err(|_| format!("{}{} is out of range", self, unit))
+    }
+}
+
+/// Accepts an optional sign and at most three decimal places, as in "-2.5".
+impl FromStr for Fixed {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Fixed, String> {
+        let invalid = || format!("Invalid fixed point number \"{}\"", text);
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if (whole.is_empty() && fraction.is_empty())
+            || fraction.len() > 3
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole = if whole.is_empty() {
+            0
+        } else {
+            whole.parse::<i64>().map_err(|_| invalid())?
+        };
+        let fraction = format!("{:0<3}", fraction).parse::<i64>().unwrap();
+        let value = whole
+            .checked_mul(Fixed::SCALE)
+            .and_then(|w| w.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        Ok(Fixed(if negative { -value } else { value }))
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let scale = Fixed::SCALE.unsigned_abs();
+        let (whole, fraction) = (abs / scale, abs % scale);
+        if fraction == 0 {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            let fraction = format!("{:03}", fraction);
+            write!(f, "{}{}.{}", sign, whole, fraction.trim_end_matches('0'))
+        }
+    }
+}
+
+#[test]
+fn test_parse_and_display() {
+    for (text, thousandths) in [
+        ("2.5", 2500),
+        ("-0.001", -1),
+        ("3", 3000),
+        ("-12.34", -12340),
+        ("0", 0),
+    ] {
+        assert_eq!(Ok(Fixed::from_thousandths(thousandths)), text.parse());
+        assert_eq!(text, Fixed::from_thousandths(thousandths).to_string());
+    }
+    assert_eq!(Ok(Fixed::from_thousandths(500)), ".5".parse());
+    for text in ["", "-", ".", "1.2345", "1e3", "1.-5", "99999999999999999"] {
+        assert_eq!(
+            Err(format!("Invalid fixed point number \"{}\"", text)),
+            text.parse::<Fixed>()
+        );
+    }
+}
+
+#[test]
+fn test_arithmetic() {
+    let fixed = |text: &str| text.parse::<Fixed>().unwrap();
+    assert_eq!(Some(fixed("0.3")), fixed("0.1").checked_add(fixed("0.2")));
+    assert_eq!(Some(fixed("3.75")), fixed("2.5").checked_mul(fixed("1.5")));
+    // 0.001 * 0.5 is half a thousandth, which rounds away from zero.
+    assert_eq!(
+        Some(fixed("-0.001")),
+        fixed("-0.001").checked_mul(fixed("0.5"))
+    );
+
+    let max = Fixed::from_thousandths(i64::MAX);
+    assert_eq!(None, max.checked_mul(fixed("2")));
+    assert_eq!(max, max.saturating_mul(fixed("2")));
+    assert_eq!(Fixed::from(7), Fixed::from(7.0004));
+}
@@ -0,0 +1,446 @@
+use crate::navigator::StatefulNavigator;
+use crate::submarine::Submarine;
+use crate::timeline::TimedCommand;
+
+/// How the command streams of a fleet are interleaved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Schedule {
+    /// Every submarine carries out its next command at the same time, ignoring timestamps.
+    LOCKSTEP,
+    /// Commands are carried out in timestamp order. Commands with the same timestamp happen
+    /// together.
+    TIMESTAMP,
+}
+
+/// One submarine of a fleet. It is launched at `offset` and tries to keep that position relative
+/// to the leader, the first member of the fleet. Members can each have a navigator of their own
+/// kind, stateful or not, such as one from `crate::registry::Registry`.
+pub struct Member {
+    pub name: String,
+    pub navigator: Box<dyn StatefulNavigator>,
+    pub commands: Vec<TimedCommand>,
+    pub offset: (i32, i32),
+}
+
+impl Member {
+    pub fn new(
+        name: &str,
+        navigator: Box<dyn StatefulNavigator>,
+        commands: Vec<TimedCommand>,
+    ) -> Member {
+        Member {
+            name: name.to_string(),
+            navigator,
+            commands,
+            offset: (0, 0),
+        }
+    }
+
+    pub fn with_offset(mut self, x: i32, depth: i32) -> Member {
+        self.offset = (x, depth);
+        self
+    }
+}
+
+/// The closest two submarines came to each other, including part way through a move. `at` is the
+/// time, or for lockstep runs the number of steps taken, which is fractional mid move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Approach {
+    pub first: usize,
+    pub second: usize,
+    pub distance: f64,
+    pub at: f64,
+}
+
+/// How far a submarine strayed from its place in the formation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FormationError {
+    pub mean: f64,
+    pub max: f64,
+}
+
+#[derive(Debug)]
+pub struct FleetReport {
+    pub submarines: Vec<Submarine>,
+    /// `None` for fleets of fewer than two submarines.
+    pub closest_approach: Option<Approach>,
+    /// One per member, measured at launch and after each step. The leader's is always zero.
+    pub formation: Vec<FormationError>,
+}
+
+#[derive(Default)]
+pub struct Fleet {
+    members: Vec<Member>,
+}
+
+impl Fleet {
+    pub fn new() -> Fleet {
+        Fleet { members: vec![] }
+    }
+
+    pub fn with(mut self, member: Member) -> Fleet {
+        self.members.push(member);
+        self
+    }
+
+    /// Runs every member's commands from launch. Each navigator is reset first, so a fleet can be
+    /// run more than once.
+    pub fn run(&mut self, schedule: Schedule) -> Result<FleetReport, String> {
+        for member in &mut self.members {
+            member.navigator.reset();
+        }
+        let mut tracker = Tracker::new(&self.members);
+        let mut subs: Vec<Submarine> = self
+            .members
+            .iter()
+            .map(|m| {
+                let mut sub = Submarine::new();
+                (sub.x, sub.depth) = m.offset;
+                sub
+            })
+            .collect();
+        tracker.observe(&subs, 0.0);
+
+        match schedule {
+            Schedule::LOCKSTEP => {
+                let steps = self.members.iter().map(|m| m.commands.len()).max();
+                for step in 0..steps.unwrap_or(0) {
+                    for (member, sub) in self.members.iter_mut().zip(&mut subs) {
+                        if let Some(&timed) = member.commands.get(step) {
+                            member.drive(sub, step, &timed)?;
+                        }
+                    }
+                    tracker.observe(&subs, (step + 1) as f64);
+                }
+            }
+            Schedule::TIMESTAMP => {
+                // (member, index in its stream, command)
+                let mut events: Vec<(usize, usize, TimedCommand)> = self
+                    .members
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(m, member)| {
+                        member
+                            .commands
+                            .iter()
+                            .enumerate()
+                            .map(move |(c, timed)| (m, c, *timed))
+                    })
+                    .collect();
+                // Stable, so each stream keeps its own order and ties go to earlier members.
+                events.sort_by(|a, b| a.2.time.total_cmp(&b.2.time));
+
+                for group in events.chunk_by(|a, b| a.2.time == b.2.time) {
+                    for (m, c, timed) in group {
+                        self.members[*m].drive(&mut subs[*m], *c, timed)?;
+                    }
+                    tracker.observe(&subs, group[0].2.time);
+                }
+            }
+        }
+
+        Ok(FleetReport {
+            submarines: subs,
+            closest_approach: tracker.closest,
+            formation: tracker.formation(),
+        })
+    }
+}
+
+impl Member {
+    fn drive(
+        &mut self,
+        sub: &mut Submarine,
+        idx: usize,
+        timed: &TimedCommand,
+    ) -> Result<(), String> {
+        self.navigator
+            .drive(sub, &timed.command)
+            .map_err(|e| format!("{}: Command {}: {}", self.name, idx, e))
+    }
+}
+
+struct Tracker {
+    offsets: Vec<(i32, i32)>,
+    // Where the submarines were at the last observation, and when.
+    previous: Option<(Vec<(f64, f64)>, f64)>,
+    closest: Option<Approach>,
+    error_sums: Vec<f64>,
+    error_maxes: Vec<f64>,
+    observations: usize,
+}
+
+impl Tracker {
+    fn new(members: &[Member]) -> Tracker {
+        Tracker {
+            offsets: members.iter().map(|m| m.offset).collect(),
+            previous: None,
+            closest: None,
+            error_sums: vec![0.0; members.len()],
+            error_maxes: vec![0.0; members.len()],
+            observations: 0,
+        }
+    }
+
+    // Submarines move in straight lines between observations, so the closest approach is found
+    // along those lines rather than only at their ends. Subs that pass through each other between
+    // two observations are caught that way.
+    fn observe(&mut self, subs: &[Submarine], at: f64) {
+        let positions: Vec<(f64, f64)> = subs
+            .iter()
+            .map(|s| (f64::from(s.x), f64::from(s.depth)))
+            .collect();
+        let (starts, since) = match &self.previous {
+            Some((starts, since)) => (starts.clone(), *since),
+            None => (positions.clone(), at),
+        };
+        for first in 0..subs.len() {
+            for second in first + 1..subs.len() {
+                let (t, distance) = closest_point(
+                    (starts[first], positions[first]),
+                    (starts[second], positions[second]),
+                );
+                if self.closest.is_none_or(|c| distance < c.distance) {
+                    self.closest = Some(Approach {
+                        first,
+                        second,
+                        distance,
+                        at: since + (at - since) * t,
+                    });
+                }
+            }
+        }
+        self.previous = Some((positions, at));
+
+        if let Some(leader) = subs.first() {
+            let (leader_x, leader_depth) = self.offsets[0];
+            for (idx, sub) in subs.iter().enumerate() {
+                let (x, depth) = self.offsets[idx];
+                let slot = (
+                    i64::from(leader.x) + i64::from(x) - i64::from(leader_x),
+                    i64::from(leader.depth) + i64::from(depth) - i64::from(leader_depth),
+                );
+                let error = distance(slot, (i64::from(sub.x), i64::from(sub.depth)));
+                self.error_sums[idx] += error;
+                self.error_maxes[idx] = self.error_maxes[idx].max(error);
+            }
+        }
+        self.observations += 1;
+    }
+
+    fn formation(&self) -> Vec<FormationError> {
+        self.error_sums
+            .iter()
+            .zip(&self.error_maxes)
+            .map(|(sum, max)| FormationError {
+                mean: sum / self.observations as f64,
+                max: *max,
+            })
+            .collect()
+    }
+}
+
+type Segment = ((f64, f64), (f64, f64));
+
+// The fraction of the way along two simultaneous moves at which they are closest, and the
+// distance between them then.
+fn closest_point(a: Segment, b: Segment) -> (f64, f64) {
+    let start = (b.0 .0 - a.0 .0, b.0 .1 - a.0 .1);
+    let end = (b.1 .0 - a.1 .0, b.1 .1 - a.1 .1);
+    let change = (end.0 - start.0, end.1 - start.1);
+    let length = change.0 * change.0 + change.1 * change.1;
+    let t = if length == 0.0 {
+        // Moving in parallel, so the distance never changes. Report the end of the move.
+        1.0
+    } else {
+        (-(start.0 * change.0 + start.1 * change.1) / length).clamp(0.0, 1.0)
+    };
+    let distance = (start.0 + change.0 * t).hypot(start.1 + change.1 * t);
+    (t, distance)
+}
+
+fn distance(a: (i64, i64), b: (i64, i64)) -> f64 {
+    ((a.0 - b.0) as f64).hypot((a.1 - b.1) as f64)
+}
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, PartOneNav};
+
+#[cfg(test)]
+fn timed(lines: Vec<&str>) -> Vec<TimedCommand> {
+    TimedCommand::parse_each(lines).unwrap()
+}
+
+#[test]
+fn test_lockstep_formation() {
+    let commands = timed(vec!["0s forward 5", "0s down 2", "0s forward 3"]);
+    let mut fleet = Fleet::new()
+        .with(Member::new(
+            "lead",
+            Box::new(AimingNav::new()),
+            commands.clone(),
+        ))
+        .with(Member::new("wing", Box::new(AimingNav::new()), commands.clone()).with_offset(-3, 4))
+        // Same commands, different navigator, so it falls out of formation on the last step.
+        .with(Member::new("stray", Box::new(PartOneNav::new()), commands).with_offset(0, 10));
+    let report = fleet.run(Schedule::LOCKSTEP).unwrap();
+
+    assert_eq!(
+        vec![(8, 6), (5, 10), (8, 12)],
+        report
+            .submarines
+            .iter()
+            .map(|s| (s.x, s.depth))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(Approach {
+            first: 1,
+            second: 2,
+            distance: 13f64.sqrt(),
+            at: 3.0,
+        }),
+        report.closest_approach
+    );
+    assert_eq!(FormationError::default(), report.formation[0]);
+    assert_eq!(FormationError::default(), report.formation[1]);
+    assert_eq!(
+        FormationError {
+            mean: 1.5,
+            max: 4.0,
+        },
+        report.formation[2]
+    );
+}
+
+#[test]
+fn test_lockstep_uneven_streams() {
+    let mut fleet = Fleet::new()
+        .with(Member::new(
+            "a",
+            Box::new(PartOneNav::new()),
+            timed(vec!["0s forward 1", "0s forward 1", "0s forward 1"]),
+        ))
+        .with(
+            Member::new("b", Box::new(PartOneNav::new()), timed(vec!["0s up 1"])).with_offset(3, 5),
+        );
+    let report = fleet.run(Schedule::LOCKSTEP).unwrap();
+    assert_eq!((3, 0), (report.submarines[0].x, report.submarines[0].depth));
+    assert_eq!((3, 4), (report.submarines[1].x, report.submarines[1].depth));
+    // "a" catches up to directly above "b" after the third step.
+    assert_eq!(
+        Some(Approach {
+            first: 0,
+            second: 1,
+            distance: 4.0,
+            at: 3.0,
+        }),
+        report.closest_approach
+    );
+}
+
+#[test]
+fn test_timestamp_order() {
+    let mut fleet = Fleet::new()
+        .with(Member::new(
+            "a",
+            Box::new(PartOneNav::new()),
+            timed(vec!["1s forward 3", "2s forward 3"]),
+        ))
+        .with(
+            Member::new(
+                "b",
+                Box::new(PartOneNav::new()),
+                timed(vec!["3s forward -4"]),
+            )
+            .with_offset(10, 0),
+        );
+    let report = fleet.run(Schedule::TIMESTAMP).unwrap();
+    assert_eq!((6, 6), (report.submarines[0].x, report.submarines[1].x));
+    assert_eq!(
+        Some(Approach {
+            first: 0,
+            second: 1,
+            distance: 0.0,
+            at: 3.0,
+        }),
+        report.closest_approach
+    );
+
+    // In lockstep "b" sets off straight away and they meet after two steps.
+    let report = fleet.run(Schedule::LOCKSTEP).unwrap();
+    assert_eq!(2.0, report.closest_approach.unwrap().at);
+}
+
+#[test]
+fn test_passing_through() {
+    let mut fleet = Fleet::new()
+        .with(Member::new(
+            "a",
+            Box::new(PartOneNav::new()),
+            timed(vec!["0s forward 10"]),
+        ))
+        .with(
+            Member::new(
+                "b",
+                Box::new(PartOneNav::new()),
+                timed(vec!["0s forward -10"]),
+            )
+            .with_offset(10, 0),
+        );
+    // They swap places, meeting half way through the step.
+    let report = fleet.run(Schedule::LOCKSTEP).unwrap();
+    assert_eq!(
+        Some(Approach {
+            first: 0,
+            second: 1,
+            distance: 0.0,
+            at: 0.5,
+        }),
+        report.closest_approach
+    );
+}
+
+#[test]
+fn test_errors_name_the_member() {
+    let mut fleet = Fleet::new()
+        .with(Member::new("a", Box::new(AimingNav::new()), vec![]))
+        .with(Member::new(
+            "b",
+            Box::new(AimingNav::new()),
+            timed(vec!["0s down 2147483647", "1s down 1"]),
+        ));
+    assert_eq!(
+        Err("b: Command 1: Arithmetic overflow: 2147483647 + 1".to_string()),
+        fleet.run(Schedule::TIMESTAMP).map(|_| ())
+    );
+    assert!(Fleet::new()
+        .run(Schedule::LOCKSTEP)
+        .unwrap()
+        .closest_approach
+        .is_none());
+}
+
+#[test]
+fn test_stateful_members() {
+    use crate::momentum::Momentum;
+    use crate::registry::Registry;
+
+    let commands = timed(vec!["0s forward 2", "1s down 1", "2s down 1"]);
+    let mut fleet = Fleet::new()
+        .with(Member::new(
+            "coasting",
+            Box::new(Momentum::new(1)),
+            commands.clone(),
+        ))
+        .with(Member::new(
+            "aiming",
+            Registry::builtin().get("aiming").unwrap(),
+            commands,
+        ));
+    let first = fleet.run(Schedule::TIMESTAMP).unwrap();
+    assert_eq!((3, 2), (first.submarines[0].x, first.submarines[0].depth));
+    // Momentum is forgotten between runs.
+    let second = fleet.run(Schedule::TIMESTAMP).unwrap();
+    assert_eq!(first.submarines, second.submarines);
+}
@@ -0,0 +1,159 @@
+use crate::command::Command;
+use crate::navigator::Navigator;
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+
+/// Prices a command by the change it makes to the submarine, so the same model works for any
+/// navigator.
+pub trait CostModel<T: Scalar> {
+    fn cost(
+        &self,
+        command: &Command,
+        before: &Submarine<T>,
+        after: &Submarine<T>,
+    ) -> Result<T, String>;
+}
+
+/// Moving forward costs the distance travelled, changing depth costs the distance dived or climbed
+/// times `pressure`, and any change of aim costs a flat `aim_change`.
+pub struct StandardCost<T> {
+    pub pressure: T,
+    pub aim_change: T,
+}
+
+impl<T: Scalar> StandardCost<T> {
+    pub fn new(pressure: T, aim_change: T) -> StandardCost<T> {
+        StandardCost {
+            pressure,
+            aim_change,
+        }
+    }
+}
+
+impl<T: Scalar> CostModel<T> for StandardCost<T> {
+    fn cost(
+        &self,
+        _command: &Command,
+        before: &Submarine<T>,
+        after: &Submarine<T>,
+    ) -> Result<T, String> {
+        let overflow = before.overflow;
+        let distance = |from: T, to: T| {
+            let delta = overflow.sub(to, from)?;
+            if delta < T::default() {
+                overflow.sub(T::default(), delta)
+            } else {
+                Ok(delta)
+            }
+        };
+
+        let forward = distance(before.x, after.x)?;
+        let vertical = overflow.mul(distance(before.depth, after.depth)?, self.pressure)?;
+        let aim = if before.aim == after.aim {
+            T::default()
+        } else {
+            self.aim_change
+        };
+        overflow.add(overflow.add(forward, vertical)?, aim)
+    }
+}
+
+/// Wraps a navigator so that each command burns fuel according to a cost model. A command the
+/// submarine can't afford is rejected before it moves the submarine. Submarines without a fuel
+/// budget are driven as if by the wrapped navigator alone.
+pub struct Fueled<N, C> {
+    navigator: N,
+    costs: C,
+}
+
+impl<N, C> Fueled<N, C> {
+    pub fn new(navigator: N, costs: C) -> Fueled<N, C> {
+        Fueled { navigator, costs }
+    }
+}
+
+impl<T: Scalar, N: Navigator<T>, C: CostModel<T>> Navigator<T> for Fueled<N, C> {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command) -> Result<(), String> {
+        let mut next = *submarine;
+        self.navigator.act_on(&mut next, command)?;
+        if let Some(fuel) = submarine.fuel {
+            let cost = self.costs.cost(command, submarine, &next)?;
+            if cost > fuel {
+                return Err(format!(
+                    "Out of fuel: \"{}\" needs {} but only {} is left",
+                    command, cost, fuel
+                ));
+            }
+            next.fuel = Some(submarine.overflow.sub(fuel, cost)?);
+        }
+        *submarine = next;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, PartOneNav};
+
+#[test]
+fn test_standard_cost() {
+    let nav = Fueled::new(AimingNav::new(), StandardCost::new(2, 3));
+    let mut sub = Submarine::with_fuel(100);
+
+    nav.act_on(&mut sub, &"forward 5".parse().unwrap()).unwrap();
+    assert_eq!(Some(95), sub.fuel);
+    nav.act_on(&mut sub, &"down 2".parse().unwrap()).unwrap();
+    assert_eq!(Some(92), sub.fuel);
+    // 4 forward plus 8 down at a pressure of 2.
+    nav.act_on(&mut sub, &"forward 4".parse().unwrap()).unwrap();
+    assert_eq!(Some(72), sub.fuel);
+    nav.act_on(&mut sub, &"forward -4".parse().unwrap())
+        .unwrap();
+    assert_eq!(Some(52), sub.fuel);
+    nav.act_on(&mut sub, &"down 0".parse().unwrap()).unwrap();
+    assert_eq!(Some(52), sub.fuel);
+}
+
+#[test]
+fn test_out_of_fuel() {
+    let commands = Command::parse_each(vec!["forward 5", "down 3", "forward 1", "up 3"]).unwrap();
+    let nav = Fueled::new(PartOneNav::new(), StandardCost::new(2, 0));
+
+    let mut sub = Submarine::with_fuel(10);
+    assert_eq!(
+        Err("Command 1: Out of fuel: \"down 3\" needs 6 but only 5 is left".to_string()),
+        nav.act_on_each(&mut sub, commands.iter())
+    );
+    assert_eq!((5, 0, Some(5)), (sub.x, sub.depth, sub.fuel));
+
+    let mut sub = Submarine::with_fuel(18);
+    nav.act_on_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!((6, 0, Some(0)), (sub.x, sub.depth, sub.fuel));
+}
+
+#[test]
+fn test_unlimited_fuel() {
+    let commands = Command::parse_each(vec!["forward 5", "down 3"]).unwrap();
+    let mut sub = Submarine::new();
+    Fueled::new(AimingNav::new(), StandardCost::new(2, 1))
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!((5, 0, 3, None), (sub.x, sub.depth, sub.aim, sub.fuel));
+}
+
+#[test]
+fn test_custom_cost_model() {
+    struct PerCommand;
+
+    impl CostModel<i32> for PerCommand {
+        fn cost(&self, _: &Command, _: &Submarine, _: &Submarine) -> Result<i32, String> {
+            Ok(1)
+        }
+    }
+
+    let commands = Command::parse_each(vec!["forward 5", "down 3", "up 3"]).unwrap();
+    let mut sub = Submarine::with_fuel(2);
+    assert_eq!(
+        Err("Command 2: Out of fuel: \"up 3\" needs 1 but only 0 is left".to_string()),
+        Fueled::new(PartOneNav::new(), PerCommand).act_on_each(&mut sub, commands.iter())
+    );
+}
@@ -0,0 +1,580 @@
+use crate::command::Command;
+use crate::navigator::StatefulNavigator;
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+use std::any::Any;
+
+/// An area of (x, depth) space. Points on the boundary are inside.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Shape {
+    RECTANGLE {
+        x: (i32, i32),
+        depth: (i32, i32),
+    },
+    /// The corners in order around the edge. The last corner joins back up with the first.
+    POLYGON(Vec<(i32, i32)>),
+}
+
+impl Shape {
+    pub fn contains(&self, x: i32, depth: i32) -> bool {
+        match self {
+            Shape::RECTANGLE {
+                x: (x0, x1),
+                depth: (d0, d1),
+            } => {
+                (*x0.min(x1)..=*x0.max(x1)).contains(&x)
+                    && (*d0.min(d1)..=*d0.max(d1)).contains(&depth)
+            }
+            Shape::POLYGON(corners) => polygon_contains(corners, x, depth),
+        }
+    }
+}
+
+// Slack for points worked out along a move, so that those landing on a boundary count as inside.
+const EPSILON: f64 = 1e-6;
+
+type Point = (f64, f64);
+
+impl Shape {
+    fn corners(&self) -> Vec<(i32, i32)> {
+        match self {
+            Shape::RECTANGLE {
+                x: (x0, x1),
+                depth: (d0, d1),
+            } => vec![(*x0, *d0), (*x1, *d0), (*x1, *d1), (*x0, *d1)],
+            Shape::POLYGON(corners) => corners.clone(),
+        }
+    }
+
+    fn edges(&self) -> Vec<(Point, Point)> {
+        let corners: Vec<Point> = self
+            .corners()
+            .iter()
+            .map(|(x, depth)| (f64::from(*x), f64::from(*depth)))
+            .collect();
+        corners
+            .iter()
+            .zip(corners.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+            .collect()
+    }
+
+    // Like `contains`, for points between whole positions.
+    fn contains_point(&self, p: Point) -> bool {
+        let edges = self.edges();
+        if edges
+            .iter()
+            .any(|(a, b)| distance_to_edge(p, *a, *b) <= EPSILON)
+        {
+            return true;
+        }
+        let mut inside = false;
+        for (a, b) in edges {
+            if (a.1 > p.1) != (b.1 > p.1) {
+                let x = a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0);
+                if x > p.0 {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    // Where a straight move from `from` to `to` enters and leaves the shape, as fractions of the
+    // way along it. The ends of the move are given, since they can be checked exactly.
+    fn crossings(
+        &self,
+        from: Point,
+        to: Point,
+        starts_inside: bool,
+        ends_inside: bool,
+    ) -> Vec<(f64, Crossing)> {
+        let d = (to.0 - from.0, to.1 - from.1);
+        let at = |t: f64| (from.0 + d.0 * t, from.1 + d.1 * t);
+        let mut ts = vec![0.0, 1.0];
+        if d != (0.0, 0.0) {
+            for (a, b) in self.edges() {
+                let e = (b.0 - a.0, b.1 - a.1);
+                let to_a = (a.0 - from.0, a.1 - from.1);
+                let denominator = cross(d, e);
+                if denominator == 0.0 {
+                    // Parallel, so the move can only run along the edge.
+                    if cross(to_a, d) == 0.0 {
+                        let length = d.0 * d.0 + d.1 * d.1;
+                        for corner in [a, b] {
+                            let offset = (corner.0 - from.0, corner.1 - from.1);
+                            ts.push((offset.0 * d.0 + offset.1 * d.1) / length);
+                        }
+                    }
+                } else {
+                    let u = cross(to_a, d) / denominator;
+                    if (-EPSILON..=1.0 + EPSILON).contains(&u) {
+                        ts.push(cross(to_a, e) / denominator);
+                    }
+                }
+            }
+        }
+        ts.retain(|t| (0.0..=1.0).contains(t));
+        ts.sort_by(f64::total_cmp);
+        ts.dedup();
+
+        // The state at each boundary point and between each pair of them, in order along the move.
+        let mut states = vec![(0.0, starts_inside)];
+        for pair in ts.windows(2) {
+            if pair[0] > 0.0 {
+                states.push((pair[0], self.contains_point(at(pair[0]))));
+            }
+            let middle = (pair[0] + pair[1]) / 2.0;
+            states.push((middle, self.contains_point(at(middle))));
+        }
+        states.push((1.0, ends_inside));
+
+        states
+            .windows(2)
+            .filter(|pair| pair[0].1 != pair[1].1)
+            // The boundary counts as inside, so a move enters at the first point inside and exits
+            // at the last.
+            .map(|pair| match pair[1].1 {
+                true => (pair[1].0, Crossing::ENTER),
+                false => (pair[0].0, Crossing::EXIT),
+            })
+            .collect()
+    }
+}
+
+fn cross(a: Point, b: Point) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn distance_to_edge(p: Point, a: Point, b: Point) -> f64 {
+    let e = (b.0 - a.0, b.1 - a.1);
+    let length = e.0 * e.0 + e.1 * e.1;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * e.0 + (p.1 - a.1) * e.1) / length).clamp(0.0, 1.0)
+    };
+    (p.0 - a.0 - e.0 * t).hypot(p.1 - a.1 - e.1 * t)
+}
+
+fn polygon_contains(corners: &[(i32, i32)], x: i32, depth: i32) -> bool {
+    let p = (i128::from(x), i128::from(depth));
+    let edges = corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(a, b)| {
+            (
+                (i128::from(a.0), i128::from(a.1)),
+                (i128::from(b.0), i128::from(b.1)),
+            )
+        });
+
+    let mut inside = false;
+    for (a, b) in edges {
+        let cross = (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+        let on_edge = cross == 0
+            && (a.0.min(b.0)..=a.0.max(b.0)).contains(&p.0)
+            && (a.1.min(b.1)..=a.1.max(b.1)).contains(&p.1);
+        if on_edge {
+            return true;
+        }
+        // Count the edges crossed by a ray heading toward increasing x.
+        if (a.1 > p.1) != (b.1 > p.1) {
+            // The side of the edge the point is on, flipped so positive means left of the ray.
+            let side = if b.1 > a.1 { cross } else { -cross };
+            if side > 0 {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Zone {
+    pub name: String,
+    pub shape: Shape,
+    pub forbidden: bool,
+}
+
+impl Zone {
+    pub fn rectangle(name: &str, corner: (i32, i32), opposite: (i32, i32)) -> Zone {
+        Zone::new(
+            name,
+            Shape::RECTANGLE {
+                x: (corner.0, opposite.0),
+                depth: (corner.1, opposite.1),
+            },
+        )
+    }
+
+    pub fn polygon(name: &str, corners: Vec<(i32, i32)>) -> Zone {
+        Zone::new(name, Shape::POLYGON(corners))
+    }
+
+    fn new(name: &str, shape: Shape) -> Zone {
+        Zone {
+            name: name.to_string(),
+            shape,
+            forbidden: false,
+        }
+    }
+
+    /// Marks the zone as off limits.
+    pub fn forbidden(mut self) -> Zone {
+        self.forbidden = true;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Crossing {
+    ENTER,
+    EXIT,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ZoneEvent {
+    /// The index of the command that crossed the boundary, counting from the last reset.
+    pub index: usize,
+    pub zone: String,
+    pub crossing: Crossing,
+}
+
+/// Wraps a navigator to watch the path the submarine takes for each command, recording an event
+/// whenever it enters or leaves one of the zones. Zones it starts in aren't reported as entered.
+/// Moves are straight lines, so passing through a thin zone part way through a command counts,
+/// and the events of a command are in the order they happen along it. Resetting forgets the
+/// events and starts counting commands from zero again.
+pub struct Geofenced<N> {
+    navigator: N,
+    zones: Vec<Zone>,
+    halt: bool,
+    index: usize,
+    events: Vec<ZoneEvent>,
+}
+
+impl<N> Geofenced<N> {
+    pub fn new(navigator: N, zones: Vec<Zone>) -> Geofenced<N> {
+        Geofenced {
+            navigator,
+            zones,
+            halt: false,
+            index: 0,
+            events: vec![],
+        }
+    }
+
+    /// Rejects any command that would take the submarine into a forbidden zone, leaving it where
+    /// it was.
+    pub fn halting(mut self) -> Geofenced<N> {
+        self.halt = true;
+        self
+    }
+
+    /// Every crossing so far, in order.
+    pub fn events(&self) -> &[ZoneEvent] {
+        &self.events
+    }
+
+    /// Entries into forbidden zones.
+    pub fn violations(&self) -> Vec<ZoneEvent> {
+        self.events
+            .iter()
+            .filter(|e| {
+                e.crossing == Crossing::ENTER
+                    && self.zones.iter().any(|z| z.forbidden && z.name == e.zone)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn inside<T: Scalar + Into<i128>>(&self, submarine: &Submarine<T>) -> Vec<bool> {
+        // Zones are in i32 space, so anything further out is outside all of them.
+        let position = i32::try_from(submarine.x.into())
+            .ok()
+            .zip(i32::try_from(submarine.depth.into()).ok());
+        self.zones
+            .iter()
+            .map(|zone| position.is_some_and(|(x, depth)| zone.shape.contains(x, depth)))
+            .collect()
+    }
+}
+
+impl<N> Geofenced<N> {
+    fn drive_geofenced<T: Scalar + Into<i128>>(
+        &mut self,
+        submarine: &mut Submarine<T>,
+        command: &Command,
+    ) -> Result<(), String>
+    where
+        N: StatefulNavigator<T>,
+    {
+        let mut next = *submarine;
+        self.navigator.drive(&mut next, command)?;
+
+        let index = self.index;
+        let point = |s: &Submarine<T>| (s.x.into() as f64, s.depth.into() as f64);
+        let mut crossings = vec![];
+        for (zone, (was, is)) in self
+            .zones
+            .iter()
+            .zip(self.inside(submarine).into_iter().zip(self.inside(&next)))
+        {
+            for (t, crossing) in zone
+                .shape
+                .crossings(point(submarine), point(&next), was, is)
+            {
+                crossings.push((t, zone, crossing));
+            }
+        }
+        // Stable, so crossings at the same point stay in zone order.
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut events = vec![];
+        for (_, zone, crossing) in crossings {
+            if crossing == Crossing::ENTER && zone.forbidden && self.halt {
+                return Err(format!("Entered forbidden zone \"{}\"", zone.name));
+            }
+            events.push(ZoneEvent {
+                index,
+                zone: zone.name.clone(),
+                crossing,
+            });
+        }
+
+        self.events.extend(events);
+        self.index += 1;
+        *submarine = next;
+        Ok(())
+    }
+}
+
+// The command count, the events so far and the wrapped navigator's own state.
+type GeofenceState = (usize, Vec<ZoneEvent>, Option<Box<dyn Any>>);
+
+// Implemented for each scalar rather than generically, since a generic impl could overlap with the
+// blanket impl for stateless navigators.
+macro_rules! impl_geofenced {
+    ($($t:ty),*) => {
+        $(
+            impl<N: StatefulNavigator<$t>> StatefulNavigator<$t> for Geofenced<N> {
+                fn drive(
+                    &mut self,
+                    submarine: &mut Submarine<$t>,
+                    command: &Command,
+                ) -> Result<(), String> {
+                    self.drive_geofenced(submarine, command)
+                }
+
+                fn reset(&mut self) {
+                    self.navigator.reset();
+                    self.index = 0;
+                    self.events.clear();
+                }
+
+                fn state(&self) -> Option<Box<dyn Any>> {
+                    Some(Box::new((self.index, self.events.clone(), self.navigator.state())))
+                }
+
+                fn restore(&mut self, state: &dyn Any) {
+                    if let Some((index, events, inner)) = state.downcast_ref::<GeofenceState>() {
+                        self.index = *index;
+                        self.events = events.clone();
+                        if let Some(inner) = inner {
+                            self.navigator.restore(inner.as_ref());
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_geofenced!(i32, i64, i128);
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, PartOneNav};
+
+#[cfg(test)]
+fn event(index: usize, zone: &str, crossing: Crossing) -> ZoneEvent {
+    ZoneEvent {
+        index,
+        zone: zone.to_string(),
+        crossing,
+    }
+}
+
+#[test]
+fn test_rectangle() {
+    let zone = Zone::rectangle("lane", (10, 5), (0, 0));
+    assert!(zone.shape.contains(0, 0));
+    assert!(zone.shape.contains(10, 5));
+    assert!(zone.shape.contains(4, 3));
+    assert!(!zone.shape.contains(11, 3));
+    assert!(!zone.shape.contains(4, -1));
+}
+
+#[test]
+fn test_polygon() {
+    // An L shape.
+    let shape = Zone::polygon("l", vec![(0, 0), (4, 0), (4, 2), (2, 2), (2, 6), (0, 6)]).shape;
+    for inside in [
+        (1, 1),
+        (3, 1),
+        (1, 5),
+        (0, 0),
+        (4, 2),
+        (2, 4),
+        (3, 2),
+        (0, 3),
+    ] {
+        assert!(shape.contains(inside.0, inside.1), "{:?}", inside);
+    }
+    for outside in [(3, 3), (5, 1), (-1, 0), (1, 7), (4, 6), (3, 6)] {
+        assert!(!shape.contains(outside.0, outside.1), "{:?}", outside);
+    }
+
+    let triangle = Zone::polygon("t", vec![(0, 0), (10, 10), (0, 10)]).shape;
+    assert!(triangle.contains(2, 5));
+    assert!(triangle.contains(5, 5));
+    assert!(!triangle.contains(6, 5));
+}
+
+#[test]
+fn test_enter_and_exit_events() {
+    let mut nav = Geofenced::new(
+        AimingNav::new(),
+        vec![
+            Zone::rectangle("shallows", (0, 0), (100, 5)),
+            Zone::polygon("wreck", vec![(10, 10), (20, 10), (15, 20)]),
+        ],
+    );
+    let commands = Command::parse_each(vec![
+        "forward 5",
+        "down 1",
+        "forward 10",
+        "up 1",
+        "forward 6",
+    ])
+    .unwrap();
+    let mut sub = Submarine::new();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!((21, 10), (sub.x, sub.depth));
+    assert_eq!(
+        vec![
+            event(2, "shallows", Crossing::EXIT),
+            event(2, "wreck", Crossing::ENTER),
+            event(4, "wreck", Crossing::EXIT),
+        ],
+        nav.events()
+    );
+    assert!(nav.violations().is_empty());
+
+    // After a reset the same run gives the same events, numbered from zero again.
+    let events = nav.events().to_vec();
+    StatefulNavigator::<i32>::reset(&mut nav);
+    assert!(nav.events().is_empty());
+    let mut sub = Submarine::new();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!(events, nav.events());
+}
+
+#[test]
+fn test_forbidden_zone() {
+    let zones = vec![
+        Zone::rectangle("cable", (5, 0), (6, 1000)).forbidden(),
+        Zone::rectangle("harbour", (0, 0), (3, 3)),
+    ];
+    let commands =
+        Command::parse_each(vec!["down 2", "forward 3", "forward 3", "forward 3"]).unwrap();
+
+    let mut nav = Geofenced::new(PartOneNav::new(), zones.clone());
+    let mut sub = Submarine::new();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!(vec![event(2, "cable", Crossing::ENTER)], nav.violations());
+    assert_eq!(3, nav.events().len());
+
+    let mut nav = Geofenced::new(PartOneNav::new(), zones).halting();
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err("Command 2: Entered forbidden zone \"cable\"".to_string()),
+        nav.drive_each(&mut sub, commands.iter())
+    );
+    assert_eq!((3, 2), (sub.x, sub.depth));
+    assert!(nav.events().is_empty());
+}
+
+#[test]
+fn test_crossing_part_way() {
+    let zones = vec![Zone::rectangle("cable", (5, 0), (6, 1000)).forbidden()];
+    let commands = Command::parse_each(vec!["down 2", "forward 10"]).unwrap();
+
+    let mut nav = Geofenced::new(PartOneNav::new(), zones.clone());
+    let mut sub = Submarine::new();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!(
+        vec![
+            event(1, "cable", Crossing::ENTER),
+            event(1, "cable", Crossing::EXIT)
+        ],
+        nav.events()
+    );
+
+    let mut nav = Geofenced::new(PartOneNav::new(), zones).halting();
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err("Command 1: Entered forbidden zone \"cable\"".to_string()),
+        nav.drive_each(&mut sub, commands.iter())
+    );
+    assert_eq!((0, 2), (sub.x, sub.depth));
+
+    // A diagonal move under aiming navigation clips the corner of a wreck, and events are in
+    // the order they happen along the move.
+    let mut nav = Geofenced::new(
+        AimingNav::new(),
+        vec![
+            Zone::polygon("wreck", vec![(4, 2), (8, 2), (8, 10)]),
+            Zone::rectangle("trench", (1, 0), (2, 100)),
+        ],
+    );
+    let mut sub = Submarine::new();
+    nav.drive_each(
+        &mut sub,
+        Command::parse_each(vec!["down 1", "forward 10"])
+            .unwrap()
+            .iter(),
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            event(1, "trench", Crossing::ENTER),
+            event(1, "trench", Crossing::EXIT),
+            event(1, "wreck", Crossing::ENTER),
+            event(1, "wreck", Crossing::EXIT),
+        ],
+        nav.events()
+    );
+}
+
+#[test]
+fn test_far_outside() {
+    let mut nav = Geofenced::new(
+        PartOneNav::new(),
+        vec![Zone::rectangle(
+            "all",
+            (i32::MIN, i32::MIN),
+            (i32::MAX, i32::MAX),
+        )],
+    );
+    let mut sub = Submarine::<i64>::default();
+    let commands = Command::parse_each(vec!["down 2147483647", "down 1", "up 1"]).unwrap();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!(
+        vec![
+            event(1, "all", Crossing::EXIT),
+            event(2, "all", Crossing::ENTER)
+        ],
+        nav.events()
+    );
+}
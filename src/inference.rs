@@ -0,0 +1,151 @@
+use crate::command::Command;
+use crate::navigator::StatefulNavigator;
+use crate::registry::Registry;
+use crate::submarine::Submarine;
+
+/// The navigators are named as they are in the registry.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Inference {
+    /// Every navigator whose replay ends exactly at the observed position.
+    MATCHED(Vec<String>),
+    /// No navigator explains the log. This is the one that comes closest, with its final position
+    /// minus the observed one.
+    NEAREST {
+        nav: String,
+        x_error: i64,
+        depth_error: i64,
+    },
+}
+
+/// Works out which of the navigators in `registry` could have moved a submarine from the origin to
+/// (`x`, `depth`) by following `commands`. Replays use 64 bit arithmetic so that a model isn't
+/// ruled out by an overflow the real guidance system may not have had.
+pub fn infer(
+    registry: &Registry<i64>,
+    commands: &[Command],
+    x: i32,
+    depth: i32,
+) -> Result<Inference, String> {
+    let mut replays = vec![];
+    let mut failures = vec![];
+    for name in registry.names() {
+        let mut sub = Submarine::<i64>::default();
+        match registry.get(name)?.drive_each(&mut sub, commands.iter()) {
+            Ok(()) => replays.push((name, sub.x - i64::from(x), sub.depth - i64::from(depth))),
+            Err(e) => failures.push(format!("{}: {}", name, e)),
+        }
+    }
+
+    let matched: Vec<String> = replays
+        .iter()
+        .filter(|(_, x_error, depth_error)| *x_error == 0 && *depth_error == 0)
+        .map(|(name, _, _)| name.to_string())
+        .collect();
+    if !matched.is_empty() {
+        return Ok(Inference::MATCHED(matched));
+    }
+
+    replays
+        .into_iter()
+        .min_by_key(|(_, x_error, depth_error)| x_error.unsigned_abs() + depth_error.unsigned_abs())
+        .map(|(name, x_error, depth_error)| Inference::NEAREST {
+            nav: name.to_string(),
+            x_error,
+            depth_error,
+        })
+        .ok_or_else(|| {
+            format!(
+                "No navigator could replay the log ({})",
+                failures.join("; ")
+            )
+        })
+}
+
+#[cfg(test)]
+use crate::navigator::AimingNav;
+
+#[cfg(test)]
+fn matched(names: &[&str]) -> Result<Inference, String> {
+    Ok(Inference::MATCHED(
+        names.iter().map(|name| name.to_string()).collect(),
+    ))
+}
+
+#[cfg(test)]
+fn sample_commands() -> Vec<Command> {
+    Command::parse_each(vec![
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+    ])
+    .unwrap()
+}
+
+#[test]
+fn test_matched() {
+    assert_eq!(
+        matched(&["part1"]),
+        infer(&Registry::builtin(), &sample_commands(), 15, 10)
+    );
+    assert_eq!(
+        matched(&["aiming"]),
+        infer(&Registry::builtin(), &sample_commands(), 15, 60)
+    );
+    assert_eq!(
+        matched(&["part1", "aiming"]),
+        infer(
+            &Registry::builtin(),
+            &Command::parse_each(vec!["forward 3"]).unwrap(),
+            3,
+            0
+        )
+    );
+}
+
+#[test]
+fn test_nearest() {
+    assert_eq!(
+        Ok(Inference::NEAREST {
+            nav: "aiming".to_string(),
+            x_error: -1,
+            depth_error: 5,
+        }),
+        infer(&Registry::builtin(), &sample_commands(), 16, 55)
+    );
+    assert_eq!(
+        Ok(Inference::NEAREST {
+            nav: "part1".to_string(),
+            x_error: 0,
+            depth_error: -2,
+        }),
+        infer(&Registry::builtin(), &sample_commands(), 15, 12)
+    );
+}
+
+#[test]
+fn test_overflow_does_not_rule_out() {
+    // The aiming replay passes through a depth of 2^32 on the way.
+    let commands =
+        Command::parse_each(vec!["down 65536", "forward 65536", "forward -65535"]).unwrap();
+    assert_eq!(
+        matched(&["part1", "aiming"]),
+        infer(&Registry::builtin(), &commands, 1, 65536)
+    );
+}
+
+#[test]
+fn test_registered_navigators() {
+    let mut registry = Registry::builtin();
+    registry.register("legacy", AimingNav::new).unwrap();
+    assert_eq!(
+        matched(&["aiming", "legacy"]),
+        infer(&registry, &sample_commands(), 15, 60)
+    );
+    assert_eq!(
+        Err("No navigator could replay the log ()".to_string()),
+        infer(&Registry::new(), &sample_commands(), 15, 60)
+    );
+}
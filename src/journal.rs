@@ -0,0 +1,347 @@
+use crate::command::Command;
+use crate::navigator::StatefulNavigator;
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+use std::any::Any;
+
+// A command with the states of the submarine either side of it, and the navigator's own state
+// after it. Keeping whole states rather than the difference between them means stepping back is
+// exact for any scalar, including `f64`, where adding a difference back doesn't always undo it.
+struct Entry<T> {
+    command: Command,
+    before: Submarine<T>,
+    after: Submarine<T>,
+    state: Option<Box<dyn Any>>,
+}
+
+/// Drives a submarine with a navigator while recording each command so that it can be undone and
+/// redone. Stepping through the journal restores the recorded states of both the submarine and a
+/// stateful navigator directly rather than replaying the commands.
+pub struct Journal<N, T = i32> {
+    navigator: N,
+    submarine: Submarine<T>,
+    entries: Vec<Entry<T>>,
+    // The number of entries currently applied to the submarine. Entries after this can be redone.
+    position: usize,
+    // The navigator's state before the first entry.
+    initial: Option<Box<dyn Any>>,
+}
+
+impl<N: StatefulNavigator<T>, T: Scalar> Journal<N, T> {
+    pub fn new(navigator: N, submarine: Submarine<T>) -> Journal<N, T> {
+        Journal {
+            initial: navigator.state(),
+            navigator,
+            submarine,
+            entries: vec![],
+            position: 0,
+        }
+    }
+
+    pub fn navigator(&self) -> &N {
+        &self.navigator
+    }
+
+    pub fn submarine(&self) -> &Submarine<T> {
+        &self.submarine
+    }
+
+    /// The number of commands currently applied.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of commands recorded, including any that have been undone.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The commands currently applied, in order.
+    pub fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.entries[..self.position].iter().map(|e| &e.command)
+    }
+
+    /// Applies `command` and records it. Any undone commands are discarded. The journal is
+    /// unchanged if the navigator rejects the command.
+    pub fn apply(&mut self, command: Command) -> Result<(), String> {
+        let before = self.submarine;
+        self.navigator.drive(&mut self.submarine, &command)?;
+        self.entries.truncate(self.position);
+        self.entries.push(Entry {
+            command,
+            before,
+            after: self.submarine,
+            state: self.navigator.state(),
+        });
+        self.position += 1;
+        Ok(())
+    }
+
+    pub fn apply_each<'a>(
+        &mut self,
+        commands: impl IntoIterator<Item = &'a Command>,
+    ) -> Result<(), String> {
+        for (idx, command) in commands.into_iter().enumerate() {
+            self.apply(*command)
+                .map_err(|e| format!("Command {}: {}", idx, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn undo(&mut self, n: usize) -> Result<(), String> {
+        if n > self.position {
+            return Err(format!(
+                "Cannot undo {} commands, only {} applied",
+                n, self.position
+            ));
+        }
+        self.position -= n;
+        if n > 0 {
+            self.submarine = self.entries[self.position].before;
+            self.restore_navigator();
+        }
+        Ok(())
+    }
+
+    pub fn redo(&mut self, n: usize) -> Result<(), String> {
+        let available = self.entries.len() - self.position;
+        if n > available {
+            return Err(format!(
+                "Cannot redo {} commands, only {} undone",
+                n, available
+            ));
+        }
+        self.position += n;
+        if n > 0 {
+            self.submarine = self.entries[self.position - 1].after;
+            self.restore_navigator();
+        }
+        Ok(())
+    }
+
+    fn restore_navigator(&mut self) {
+        let state = match self.position {
+            0 => &self.initial,
+            position => &self.entries[position - 1].state,
+        };
+        if let Some(state) = state {
+            self.navigator.restore(state.as_ref());
+        }
+    }
+
+    /// Moves to the state after the first `index` commands, undoing or redoing as needed.
+    pub fn rewind_to(&mut self, index: usize) -> Result<(), String> {
+        if index > self.entries.len() {
+            return Err(format!(
+                "Cannot rewind to command {}, only {} recorded",
+                index,
+                self.entries.len()
+            ));
+        }
+        if index < self.position {
+            self.undo(self.position - index)
+        } else {
+            self.redo(index - self.position)
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::AimingNav;
+
+#[cfg(test)]
+fn sample_journal() -> Journal<AimingNav> {
+    let mut journal = Journal::new(AimingNav::new(), Submarine::new());
+    journal
+        .apply_each(
+            &Command::parse_each(vec![
+                "forward 5",
+                "down 5",
+                "forward 8",
+                "up 3",
+                "down 8",
+                "forward 2",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+    journal
+}
+
+#[cfg(test)]
+fn state<N>(journal: &Journal<N>) -> (i32, i32, i32) {
+    let sub = journal.submarine;
+    (sub.x, sub.depth, sub.aim)
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut journal = sample_journal();
+    assert_eq!((15, 60, 10), state(&journal));
+    assert_eq!(6, journal.position());
+
+    journal.undo(1).unwrap();
+    assert_eq!((13, 40, 10), state(&journal));
+    journal.undo(2).unwrap();
+    assert_eq!((13, 40, 5), state(&journal));
+    assert_eq!(3, journal.position());
+    assert_eq!(3, journal.commands().count());
+
+    journal.redo(3).unwrap();
+    assert_eq!((15, 60, 10), state(&journal));
+
+    assert_eq!(
+        Err("Cannot undo 7 commands, only 6 applied".to_string()),
+        journal.undo(7)
+    );
+    assert_eq!(
+        Err("Cannot redo 1 commands, only 0 undone".to_string()),
+        journal.redo(1)
+    );
+    assert_eq!((15, 60, 10), state(&journal));
+}
+
+#[test]
+fn test_rewind_to() {
+    let mut journal = sample_journal();
+    journal.rewind_to(0).unwrap();
+    assert_eq!((0, 0, 0), state(&journal));
+    journal.rewind_to(3).unwrap();
+    assert_eq!((13, 40, 5), state(&journal));
+    journal.rewind_to(6).unwrap();
+    assert_eq!((15, 60, 10), state(&journal));
+    assert_eq!(
+        Err("Cannot rewind to command 7, only 6 recorded".to_string()),
+        journal.rewind_to(7)
+    );
+}
+
+#[test]
+fn test_apply_discards_redo() {
+    let mut journal = sample_journal();
+    journal.rewind_to(2).unwrap();
+    journal.apply("forward 1".parse().unwrap()).unwrap();
+    assert_eq!(3, journal.len());
+    assert_eq!((6, 5, 5), state(&journal));
+    assert_eq!(
+        Err("Cannot redo 1 commands, only 0 undone".to_string()),
+        journal.redo(1)
+    );
+}
+
+#[test]
+fn test_saturated_state_is_restored() {
+    use crate::scalar::Overflow;
+
+    let mut journal = Journal::new(
+        AimingNav::new(),
+        Submarine::with_overflow(Overflow::SATURATING),
+    );
+    journal
+        .apply_each(&Command::parse_each(vec!["down 2147483647", "down 5", "up 5"]).unwrap())
+        .unwrap();
+    assert_eq!(i32::MAX - 5, journal.submarine().aim);
+    journal.undo(2).unwrap();
+    assert_eq!(i32::MAX, journal.submarine().aim);
+    journal.redo(2).unwrap();
+    assert_eq!(i32::MAX - 5, journal.submarine().aim);
+}
+
+#[test]
+fn test_rejected_command_is_not_recorded() {
+    let mut journal = Journal::new(AimingNav::new(), Submarine::new());
+    assert_eq!(
+        Err("Command 1: Arithmetic overflow: 2147483647 + 1".to_string()),
+        journal.apply_each(&Command::parse_each(vec!["down 2147483647", "down 1"]).unwrap())
+    );
+    assert_eq!(1, journal.len());
+    assert_eq!(i32::MAX, journal.submarine().aim);
+}
+
+#[test]
+fn test_fuel_is_restored() {
+    use crate::fuel::{Fueled, StandardCost};
+
+    let mut journal = Journal::new(
+        Fueled::new(AimingNav::new(), StandardCost::new(1, 1)),
+        Submarine::with_fuel(100),
+    );
+    journal
+        .apply_each(&Command::parse_each(vec!["down 2", "forward 3"]).unwrap())
+        .unwrap();
+    assert_eq!(Some(90), journal.submarine().fuel);
+    journal.undo(1).unwrap();
+    assert_eq!(Some(99), journal.submarine().fuel);
+    journal.redo(1).unwrap();
+    assert_eq!(Some(90), journal.submarine().fuel);
+}
+
+#[test]
+fn test_stateful_navigator_follows_undo() {
+    use crate::momentum::Momentum;
+
+    let mut journal = Journal::new(Momentum::new(0), Submarine::new());
+    journal
+        .apply_each(&Command::parse_each(vec!["forward 2", "forward 3"]).unwrap())
+        .unwrap();
+    assert_eq!(5, journal.navigator().speed());
+    // Undoing the second thrust leaves the submarine coasting at 2.
+    journal.undo(1).unwrap();
+    journal.apply("down 1".parse().unwrap()).unwrap();
+    assert_eq!(2, journal.navigator().speed());
+    assert_eq!((4, 1), (journal.submarine().x, journal.submarine().depth));
+
+    journal.rewind_to(0).unwrap();
+    assert_eq!(0, journal.navigator().speed());
+    journal.redo(2).unwrap();
+    assert_eq!(2, journal.navigator().speed());
+}
+
+#[test]
+fn test_wrapped_state_follows_undo() {
+    use crate::geofence::{Geofenced, Zone};
+    use crate::momentum::Momentum;
+
+    let zones = vec![Zone::rectangle("reef", (5, 0), (10, 10))];
+    let mut journal = Journal::new(Geofenced::new(Momentum::new(0), zones), Submarine::new());
+    journal
+        .apply_each(&Command::parse_each(vec!["forward 3", "down 1", "down 1"]).unwrap())
+        .unwrap();
+    assert_eq!(1, journal.navigator().events().len());
+    journal.undo(2).unwrap();
+    assert!(journal.navigator().events().is_empty());
+    // The speed of 3 is restored along with the events, so coasting reaches the reef again.
+    journal.apply("down 2".parse().unwrap()).unwrap();
+    assert_eq!((6, 2), (journal.submarine().x, journal.submarine().depth));
+    assert_eq!(1, journal.navigator().events()[0].index);
+}
+
+#[test]
+fn test_floating_point_state_is_restored() {
+    // The last forward dives 2147483647 * 2147483647, which swamps the depth of 1 it started
+    // from, so taking the change in depth back off would leave the submarine at 0.
+    let mut journal = Journal::new(AimingNav::new(), Submarine::<f64>::default());
+    journal
+        .apply_each(
+            &Command::parse_each(vec![
+                "down 1",
+                "forward 1",
+                "down 2147483646",
+                "forward 2147483647",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+    journal.undo(1).unwrap();
+    assert_eq!(
+        (1.0, 1.0),
+        (journal.submarine().x, journal.submarine().depth)
+    );
+    journal.redo(1).unwrap();
+    assert_eq!(2147483648.0, journal.submarine().x);
+    assert_eq!(1.0 + 2147483647.0 * 2147483647.0, journal.submarine().depth);
+}
@@ -1,4 +1,28 @@
+pub mod autopilot;
+pub mod checkpoint;
+pub mod collision;
 pub mod columniterator;
 pub mod command;
+pub mod conditional;
+pub mod effect;
+pub mod environment;
+pub mod fixed;
+pub mod fleet;
+pub mod fuel;
+pub mod geofence;
+pub mod inference;
+pub mod journal;
+pub mod lint;
+pub mod merge;
+pub mod momentum;
 pub mod navigator;
+pub mod observer;
+pub mod optimizer;
+pub mod planner;
+pub mod registry;
+pub mod scalar;
+pub mod script;
 pub mod submarine;
+pub mod timeline;
+pub mod units;
+pub mod varint;
@@ -0,0 +1,355 @@
+use crate::command::{Action, Command};
+use crate::navigator::StatefulNavigator;
+use crate::registry::Registry;
+use crate::scalar::Overflow;
+use crate::submarine::Submarine;
+use std::fmt;
+use std::str::FromStr;
+
+// Command logs may carry comments, which also switch rules off:
+//
+//     down 0         # lint: allow zero
+//     # lint: allow negative, large
+//     forward -5000
+//     # lint: disable no-forward
+//
+// `allow` on a line of its own applies to the next command, and `disable` applies to the rest of
+// the log.
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Rule {
+    NEGATIVE,
+    ZERO,
+    LARGE,
+    SURFACING,
+    NOFORWARD,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::NEGATIVE,
+        Rule::ZERO,
+        Rule::LARGE,
+        Rule::SURFACING,
+        Rule::NOFORWARD,
+    ];
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Rule, String> {
+        match name {
+            "negative" => Ok(Rule::NEGATIVE),
+            "zero" => Ok(Rule::ZERO),
+            "large" => Ok(Rule::LARGE),
+            "surfacing" => Ok(Rule::SURFACING),
+            "no-forward" => Ok(Rule::NOFORWARD),
+            _ => Err(format!("Unknown lint rule \"{}\"", name)),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Rule::NEGATIVE => "negative",
+            Rule::ZERO => "zero",
+            Rule::LARGE => "large",
+            Rule::SURFACING => "surfacing",
+            Rule::NOFORWARD => "no-forward",
+        })
+    }
+}
+
+/// A likely mistake in a command log. Lines are numbered from 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warning {
+    pub line: usize,
+    pub rule: Rule,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {} [{}]", self.line, self.message, self.rule)
+    }
+}
+
+/// Checks command logs for values that are valid but almost always operator mistakes. Every rule
+/// is on by default.
+pub struct Linter {
+    rules: Vec<Rule>,
+    max_value: i32,
+    max_run: usize,
+    registry: Registry<i64>,
+    navigator: String,
+}
+
+impl Linter {
+    pub fn new() -> Linter {
+        Linter {
+            rules: Rule::ALL.to_vec(),
+            max_value: 1000,
+            max_run: 10,
+            registry: Registry::builtin(),
+            navigator: "part1".to_string(),
+        }
+    }
+
+    pub fn without(mut self, rule: Rule) -> Linter {
+        self.rules.retain(|r| *r != rule);
+        self
+    }
+
+    /// Values larger than this, in either direction, are reported by the `large` rule.
+    pub fn with_max_value(mut self, max_value: i32) -> Linter {
+        self.max_value = max_value;
+        self
+    }
+
+    /// Runs of more than this many commands without a `forward` are reported by `no-forward`.
+    pub fn with_max_run(mut self, max_run: usize) -> Linter {
+        self.max_run = max_run;
+        self
+    }
+
+    /// The name of the navigator used to follow the depth for the `surfacing` rule.
+    pub fn with_navigator(mut self, name: &str) -> Linter {
+        self.navigator = name.to_string();
+        self
+    }
+
+    /// Where the navigator is looked up, which is the built in navigators by default.
+    pub fn with_registry(mut self, registry: Registry<i64>) -> Linter {
+        self.registry = registry;
+        self
+    }
+
+    pub fn lint<'a>(
+        &self,
+        lines: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<Warning>, String> {
+        let mut navigator = self.registry.get(&self.navigator)?;
+        let mut warnings = vec![];
+        let mut disabled = vec![];
+        let mut allowed_next = vec![];
+        let mut sub = Submarine::<i64>::with_overflow(Overflow::SATURATING);
+        let mut run = 0;
+
+        for (idx, raw) in lines.into_iter().enumerate() {
+            let line = idx + 1;
+            let (text, comment) = match raw.split_once('#') {
+                Some((text, comment)) => (text.trim(), Some(comment)),
+                None => (raw.trim(), None),
+            };
+            let (mut allowed, disabling) = match comment {
+                Some(comment) => {
+                    suppressions(comment).map_err(|e| format!("Line {}: {}", line, e))?
+                }
+                None => (vec![], false),
+            };
+            if disabling {
+                disabled.append(&mut allowed);
+            }
+            if text.is_empty() {
+                allowed_next.append(&mut allowed);
+                continue;
+            }
+
+            let command = text
+                .parse::<Command>()
+                .map_err(|e| format!("Line {}: {}", line, e))?;
+            allowed.append(&mut allowed_next);
+            let mut warn = |rule: Rule, message: String| {
+                if self.rules.contains(&rule)
+                    && !disabled.contains(&rule)
+                    && !allowed.contains(&rule)
+                {
+                    warnings.push(Warning {
+                        line,
+                        rule,
+                        message,
+                    });
+                }
+            };
+
+            if command.value < 0 {
+                warn(Rule::NEGATIVE, format!("\"{}\" has a negative value", text));
+            }
+            if command.value == 0 {
+                warn(Rule::ZERO, format!("\"{}\" has no effect", text));
+            }
+            if command.value.unsigned_abs() > self.max_value.unsigned_abs() {
+                warn(
+                    Rule::LARGE,
+                    format!("\"{}\" is larger than {}", text, self.max_value),
+                );
+            }
+
+            let depth = sub.depth;
+            navigator
+                .drive(&mut sub, &command)
+                .map_err(|e| format!("Line {}: {}", line, e))?;
+            if sub.depth < depth && sub.depth <= 0 {
+                warn(
+                    Rule::SURFACING,
+                    format!("\"{}\" surfaces the submarine at depth {}", text, sub.depth),
+                );
+            }
+
+            if command.action == Action::FORWARD {
+                run = 0;
+            } else {
+                run += 1;
+                if run == self.max_run + 1 {
+                    warn(
+                        Rule::NOFORWARD,
+                        format!(
+                            "More than {} commands in a row without a forward",
+                            self.max_run
+                        ),
+                    );
+                }
+            }
+        }
+        Ok(warnings)
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Linter::new()
+    }
+}
+
+// The rules named by a "lint:" comment, and whether they are disabled for the rest of the log
+// rather than just allowed.
+fn suppressions(comment: &str) -> Result<(Vec<Rule>, bool), String> {
+    let Some(directive) = comment.trim().strip_prefix("lint:") else {
+        return Ok((vec![], false));
+    };
+    let directive = directive.trim();
+    let (keyword, names) = directive.split_once(' ').unwrap_or((directive, ""));
+    let disabling = match keyword {
+        "allow" => false,
+        "disable" => true,
+        _ => return Err(format!("Unknown lint directive \"{}\"", keyword)),
+    };
+    let rules = names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<Rule>, String>>()?;
+    if rules.is_empty() {
+        return Err(format!("\"lint: {}\" names no rules", keyword));
+    }
+    Ok((rules, disabling))
+}
+
+#[cfg(test)]
+fn lines(warnings: &[Warning]) -> Vec<(usize, Rule)> {
+    warnings.iter().map(|w| (w.line, w.rule)).collect()
+}
+
+#[test]
+fn test_rules() {
+    let log = vec![
+        "forward 5",
+        "down 0",
+        "forward -1",
+        "down 5000",
+        "up 4000",
+        "up 1500",
+        "forward 2",
+    ];
+    let warnings = Linter::new().lint(log.clone()).unwrap();
+    assert_eq!(
+        vec![
+            (2, Rule::ZERO),
+            (3, Rule::NEGATIVE),
+            (4, Rule::LARGE),
+            (5, Rule::LARGE),
+            (6, Rule::LARGE),
+            (6, Rule::SURFACING),
+        ],
+        lines(&warnings)
+    );
+    assert_eq!(
+        "Line 6: \"up 1500\" surfaces the submarine at depth -500 [surfacing]",
+        warnings[5].to_string()
+    );
+
+    let warnings = Linter::new()
+        .without(Rule::LARGE)
+        .with_navigator("aiming")
+        .lint(log)
+        .unwrap();
+    // Aiming up doesn't move the submarine, but the last forward takes it to -1000.
+    assert_eq!(
+        vec![(2, Rule::ZERO), (3, Rule::NEGATIVE), (7, Rule::SURFACING)],
+        lines(&warnings)
+    );
+    assert_eq!(
+        Err("Unknown navigator \"sideways\"".to_string()),
+        Linter::new()
+            .with_navigator("sideways")
+            .lint(vec!["down 1"])
+    );
+}
+
+#[test]
+fn test_runs_without_forward() {
+    let mut log = vec!["down 1"; 5];
+    log.push("forward 1");
+    log.extend(["down 1"; 7]);
+    let warnings = Linter::new().with_max_run(3).lint(log).unwrap();
+    assert_eq!(
+        vec![(4, Rule::NOFORWARD), (10, Rule::NOFORWARD)],
+        lines(&warnings)
+    );
+    assert_eq!(
+        "Line 4: More than 3 commands in a row without a forward [no-forward]",
+        warnings[0].to_string()
+    );
+}
+
+#[test]
+fn test_suppression_comments() {
+    let log = vec![
+        "# Outbound leg",
+        "down 0  # lint: allow zero",
+        "# lint: allow negative, large",
+        "",
+        "forward -5000",
+        "forward -1",
+        "# lint: disable zero",
+        "up 0",
+        "down 0",
+    ];
+    assert_eq!(
+        vec![(6, Rule::NEGATIVE)],
+        lines(&Linter::new().lint(log).unwrap())
+    );
+
+    assert_eq!(
+        Err("Line 1: Unknown lint rule \"shallow\"".to_string()),
+        Linter::new().lint(vec!["down 1 # lint: allow shallow"])
+    );
+    assert_eq!(
+        Err("Line 1: Unknown lint directive \"ignore\"".to_string()),
+        Linter::new().lint(vec!["# lint: ignore zero"])
+    );
+    assert_eq!(
+        Err("Line 2: Invalid command value: \"x\"".to_string()),
+        Linter::new().lint(vec!["down 1", "up x"])
+    );
+}
+
+#[test]
+fn test_rule_names() {
+    for rule in Rule::ALL {
+        assert_eq!(Ok(rule), rule.to_string().parse());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::command::{Action, Command};
+use crate::timeline::TimedCommand;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A timestamped command log from one system, such as the helm or the ballast.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Log {
+    pub name: String,
+    pub commands: Vec<TimedCommand>,
+}
+
+impl Log {
+    pub fn new(name: &str, commands: Vec<TimedCommand>) -> Log {
+        Log {
+            name: name.to_string(),
+            commands,
+        }
+    }
+
+    pub fn parse(name: &str, contents: &str) -> Result<Log, String> {
+        let commands =
+            TimedCommand::parse_each(contents.lines()).map_err(|e| format!("{}: {}", name, e))?;
+        Ok(Log::new(name, commands))
+    }
+
+    /// Reads a log from a file, named after its path.
+    pub fn read(path: &Path) -> Result<Log, String> {
+        let name = path.display().to_string();
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", name, e))?;
+        Log::parse(&name, &contents)
+    }
+}
+
+/// A command in the merged stream, with the name of the log it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub source: String,
+    pub timed: TimedCommand,
+}
+
+/// Two commands at the same instant that push the submarine in opposite directions, like `up`
+/// and `down`, or `forward` with values of opposite signs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub time: f64,
+    pub first: Entry,
+    pub second: Entry,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "At {}s {} \"{}\" conflicts with {} \"{}\"",
+            self.time,
+            self.first.source,
+            self.first.timed.command,
+            self.second.source,
+            self.second.timed.command
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merged {
+    pub entries: Vec<Entry>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Merged {
+    /// The merged commands in order, ready for `Navigator::act_on_each`.
+    pub fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.entries.iter().map(|entry| &entry.timed.command)
+    }
+
+    pub fn timed(&self) -> Vec<TimedCommand> {
+        self.entries.iter().map(|entry| entry.timed).collect()
+    }
+}
+
+/// Interleaves several logs into one stream ordered by time. Commands at the same instant keep the
+/// order of the logs they came from, in the order the logs were added.
+pub struct Merger {
+    logs: Vec<Log>,
+    strict: bool,
+}
+
+impl Merger {
+    pub fn new() -> Merger {
+        Merger {
+            logs: vec![],
+            strict: false,
+        }
+    }
+
+    pub fn with(mut self, log: Log) -> Merger {
+        self.logs.push(log);
+        self
+    }
+
+    /// Fails on the first conflict rather than reporting them alongside the merged stream.
+    pub fn strict(mut self) -> Merger {
+        self.strict = true;
+        self
+    }
+
+    pub fn merge(&self) -> Result<Merged, String> {
+        let mut entries: Vec<Entry> = self
+            .logs
+            .iter()
+            .flat_map(|log| {
+                log.commands.iter().map(|timed| Entry {
+                    source: log.name.clone(),
+                    timed: *timed,
+                })
+            })
+            .collect();
+        // A stable sort, so ties stay in log order.
+        entries.sort_by(|a, b| a.timed.time.total_cmp(&b.timed.time));
+
+        let mut conflicts = vec![];
+        for (idx, entry) in entries.iter().enumerate() {
+            for other in entries[idx + 1..]
+                .iter()
+                .take_while(|other| other.timed.time == entry.timed.time)
+            {
+                if opposed(&entry.timed.command, &other.timed.command) {
+                    let conflict = Conflict {
+                        time: entry.timed.time,
+                        first: entry.clone(),
+                        second: other.clone(),
+                    };
+                    if self.strict {
+                        return Err(conflict.to_string());
+                    }
+                    conflicts.push(conflict);
+                }
+            }
+        }
+        Ok(Merged { entries, conflicts })
+    }
+}
+
+impl Default for Merger {
+    fn default() -> Self {
+        Merger::new()
+    }
+}
+
+// The axis a command moves along, and which way: 0 for forward and 1 for vertical, with a
+// positive direction for forward and down.
+fn direction(command: &Command) -> (u8, i32) {
+    match command.action {
+        Action::FORWARD => (0, command.value.signum()),
+        Action::DOWN => (1, command.value.signum()),
+        Action::UP => (1, -command.value.signum()),
+    }
+}
+
+fn opposed(a: &Command, b: &Command) -> bool {
+    let ((axis_a, sign_a), (axis_b, sign_b)) = (direction(a), direction(b));
+    axis_a == axis_b && sign_a * sign_b < 0
+}
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, Navigator};
+#[cfg(test)]
+use crate::submarine::Submarine;
+
+#[cfg(test)]
+fn helm_and_ballast() -> Merger {
+    let helm = Log::parse("helm", "0s forward 5\n2s forward 8\n4s forward 2").unwrap();
+    let ballast = Log::parse("ballast", "1s down 5\n2s down 3\n3s up 3\n4s down 5").unwrap();
+    Merger::new().with(helm).with(ballast)
+}
+
+#[test]
+fn test_interleave() {
+    let merged = helm_and_ballast().merge().unwrap();
+    assert_eq!(
+        vec![
+            "helm 0s forward 5",
+            "ballast 1s down 5",
+            "helm 2s forward 8",
+            "ballast 2s down 3",
+            "ballast 3s up 3",
+            "helm 4s forward 2",
+            "ballast 4s down 5",
+        ],
+        merged
+            .entries
+            .iter()
+            .map(|e| format!("{} {}", e.source, e.timed))
+            .collect::<Vec<_>>()
+    );
+    assert!(merged.conflicts.is_empty());
+
+    let mut sub = Submarine::new();
+    AimingNav::new()
+        .act_on_each(&mut sub, merged.commands())
+        .unwrap();
+    assert_eq!((15, 50, 10), (sub.x, sub.depth, sub.aim));
+}
+
+#[test]
+fn test_conflicts() {
+    let trim = Log::parse("trim", "3s down 1\n4s forward -1\n4s up 2").unwrap();
+    let merger = helm_and_ballast().with(trim);
+    let merged = merger.merge().unwrap();
+    assert_eq!(
+        vec![
+            "At 3s ballast \"up 3\" conflicts with trim \"down 1\"",
+            "At 4s helm \"forward 2\" conflicts with trim \"forward -1\"",
+            "At 4s ballast \"down 5\" conflicts with trim \"up 2\"",
+        ],
+        merged
+            .conflicts
+            .iter()
+            .map(Conflict::to_string)
+            .collect::<Vec<_>>()
+    );
+    // Conflicting commands are still merged in log order.
+    assert_eq!(10, merged.entries.len());
+
+    assert_eq!(
+        Err("At 3s ballast \"up 3\" conflicts with trim \"down 1\"".to_string()),
+        merger.strict().merge()
+    );
+}
+
+#[test]
+fn test_parse_errors_name_the_log() {
+    assert_eq!(
+        Err("ballast: Line 2: 1s is before the previous command at 2s".to_string()),
+        Log::parse("ballast", "2s down 1\n1s up 1")
+    );
+}
@@ -0,0 +1,204 @@
+use crate::command::{Action, Command};
+use crate::fixed::Fixed;
+use crate::navigator::StatefulNavigator;
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+use std::any::Any;
+
+/// A navigator with inertia. `forward` is thrust: it adds to the submarine's speed rather than
+/// moving it directly. After every command the submarine coasts forward at its current speed, and
+/// any command other than `forward` lets the speed fall `drag` closer to zero. `up` and `down`
+/// change depth directly.
+pub struct Momentum<T = i32> {
+    drag: T,
+    speed: T,
+}
+
+impl<T: Scalar> Momentum<T> {
+    pub fn new(drag: T) -> Momentum<T> {
+        Momentum {
+            drag,
+            speed: T::default(),
+        }
+    }
+
+    pub fn speed(&self) -> T {
+        self.speed
+    }
+
+    /// Lets a tick pass without a command, so the submarine slows down as it coasts.
+    pub fn idle(&mut self, submarine: &mut Submarine<T>) -> Result<(), String> {
+        let speed = self.decayed(submarine)?;
+        self.coast(submarine, speed)
+    }
+
+    fn decayed(&self, submarine: &Submarine<T>) -> Result<T, String> {
+        let overflow = submarine.overflow;
+        let zero = T::default();
+        let minus_drag = overflow.sub(zero, self.drag)?;
+        Ok(if self.speed > self.drag {
+            overflow.sub(self.speed, self.drag)?
+        } else if self.speed < minus_drag {
+            overflow.add(self.speed, self.drag)?
+        } else {
+            zero
+        })
+    }
+
+    // Stages the move on a copy so that a failed command leaves both the submarine and the
+    // speed as they were.
+    fn coast(&mut self, submarine: &mut Submarine<T>, speed: T) -> Result<(), String> {
+        let mut next = *submarine;
+        next.forward(speed)?;
+        *submarine = next;
+        self.speed = speed;
+        Ok(())
+    }
+}
+
+impl<T: Scalar> Momentum<T> {
+    fn drive_momentum(
+        &mut self,
+        submarine: &mut Submarine<T>,
+        command: &Command,
+    ) -> Result<(), String> {
+        let value = T::from(command.value);
+        let mut next = *submarine;
+        let speed = match command.action {
+            Action::FORWARD => submarine.overflow.add(self.speed, value)?,
+            Action::UP => {
+                next.ascend(value)?;
+                self.decayed(submarine)?
+            }
+            Action::DOWN => {
+                next.dive(value)?;
+                self.decayed(submarine)?
+            }
+        };
+        self.coast(&mut next, speed)?;
+        *submarine = next;
+        Ok(())
+    }
+}
+
+// Implemented for each scalar rather than generically, since a generic impl could overlap with the
+// blanket impl for stateless navigators. Everything else about momentum is generic, so this only
+// needs to list the scalars.
+macro_rules! impl_momentum {
+    ($($t:ty),*) => {
+        $(
+            impl StatefulNavigator<$t> for Momentum<$t> {
+                fn drive(
+                    &mut self,
+                    submarine: &mut Submarine<$t>,
+                    command: &Command,
+                ) -> Result<(), String> {
+                    self.drive_momentum(submarine, command)
+                }
+
+                fn reset(&mut self) {
+                    self.speed = <$t>::default();
+                }
+
+                fn state(&self) -> Option<Box<dyn Any>> {
+                    Some(Box::new(self.speed))
+                }
+
+                fn restore(&mut self, state: &dyn Any) {
+                    if let Some(speed) = state.downcast_ref::<$t>() {
+                        self.speed = *speed;
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_momentum!(i32, i64, i128, f64, Fixed);
+
+#[cfg(test)]
+use crate::navigator::PartOneNav;
+
+#[test]
+fn test_momentum() {
+    let mut nav = Momentum::new(1);
+    let mut sub = Submarine::new();
+    let mut xs = vec![];
+    for command in Command::parse_each(vec!["forward 2", "forward 1", "down 4"]).unwrap() {
+        nav.drive(&mut sub, &command).unwrap();
+        xs.push((sub.x, nav.speed()));
+    }
+    for _ in 0..3 {
+        nav.idle(&mut sub).unwrap();
+        xs.push((sub.x, nav.speed()));
+    }
+    assert_eq!(vec![(2, 2), (5, 3), (7, 2), (8, 1), (8, 0), (8, 0)], xs);
+    assert_eq!(4, sub.depth);
+
+    // Reversing thrust slows down first.
+    let mut sub = Submarine::new();
+    nav.drive_each(
+        &mut sub,
+        Command::parse_each(vec!["forward 3", "forward -5", "up 1"])
+            .unwrap()
+            .iter(),
+    )
+    .unwrap();
+    assert_eq!((0, -1, -1), (sub.x, sub.depth, nav.speed()));
+}
+
+#[test]
+fn test_reset() {
+    let mut nav = Momentum::new(2);
+    let mut sub = Submarine::new();
+    nav.drive(&mut sub, &"forward 10".parse().unwrap()).unwrap();
+    nav.reset();
+    assert_eq!(0, nav.speed());
+    nav.idle(&mut sub).unwrap();
+    assert_eq!(10, sub.x);
+}
+
+#[test]
+fn test_failed_command_keeps_speed() {
+    let mut nav = Momentum::new(1);
+    let mut sub = Submarine::new();
+    nav.drive(&mut sub, &"forward 5".parse().unwrap()).unwrap();
+    sub.depth = i32::MAX;
+    assert_eq!(
+        Err("Command 0: Arithmetic overflow: 2147483647 + 1".to_string()),
+        nav.drive_each(
+            &mut sub,
+            Command::parse_each(vec!["down 1"]).unwrap().iter()
+        )
+    );
+    assert_eq!((5, 5), (sub.x, nav.speed()));
+}
+
+#[test]
+fn test_stateless_navigators_are_stateful_too() {
+    let commands = Command::parse_each(vec!["forward 5", "down 5"]).unwrap();
+    let mut nav = PartOneNav::new();
+    let mut sub = Submarine::new();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    StatefulNavigator::<i32>::reset(&mut nav);
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!((10, 10), (sub.x, sub.depth));
+}
+
+#[test]
+fn test_fractional_scalars() {
+    let commands = Command::parse_each(vec!["forward 3", "down 1"]).unwrap();
+    let mut nav = Momentum::new(0.5);
+    let mut sub = Submarine::<f64>::default();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!((5.5, 2.5), (sub.x, nav.speed()));
+    nav.reset();
+    assert_eq!(0.0, nav.speed());
+
+    let mut nav = Momentum::new(Fixed::from(0.25));
+    let mut sub = Submarine::<Fixed>::default();
+    nav.drive_each(&mut sub, commands.iter()).unwrap();
+    assert_eq!((Fixed::from(5.75), Fixed::from(2.75)), (sub.x, nav.speed()));
+    nav.reset();
+    assert_eq!(Fixed::default(), nav.speed());
+}
@@ -1,18 +1,145 @@
 use crate::command::{Action, Command};
+use crate::fixed::Fixed;
+use crate::scalar::Scalar;
 use crate::submarine::Submarine;
+use std::any::Any;
 
-pub trait Navigator {
-    fn act_on(&self, submarine: &mut Submarine, command: &Command);
+/// Moves a submarine in response to commands. The trait can be used as `dyn Navigator`, and boxed
+/// or borrowed navigators are navigators themselves, so `act_on_each` is available on those too.
+/// `V` is the type of the command values, which is whole metres unless the log has fractions.
+pub trait Navigator<T: Scalar = i32, V = i32> {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command<V>) -> Result<(), String>;
+
+    /// The built in motion this navigator follows, for tools such as `crate::planner` that work
+    /// out what commands do rather than carrying them out. `None` for any other navigator.
+    fn kind(&self) -> Option<NavigatorKind> {
+        None
+    }
 
     fn act_on_each<'a>(
         &self,
-        submarine: &mut Submarine,
+        submarine: &mut Submarine<T>,
+        commands: impl Iterator<Item = &'a Command<V>>,
+    ) -> Result<(), String>
+    where
+        Self: Sized,
+        V: 'a,
+    {
+        for (idx, command) in commands.enumerate() {
+            self.act_on(submarine, command)
+                .map_err(|e| format!("Command {}: {}", idx, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Scalar, V, N: Navigator<T, V> + ?Sized> Navigator<T, V> for Box<N> {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command<V>) -> Result<(), String> {
+        (**self).act_on(submarine, command)
+    }
+
+    fn kind(&self) -> Option<NavigatorKind> {
+        Navigator::<T, V>::kind(&**self)
+    }
+}
+
+impl<T: Scalar, V, N: Navigator<T, V> + ?Sized> Navigator<T, V> for &N {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command<V>) -> Result<(), String> {
+        (**self).act_on(submarine, command)
+    }
+
+    fn kind(&self) -> Option<NavigatorKind> {
+        Navigator::<T, V>::kind(&**self)
+    }
+}
+
+/// A navigator with memory of its own, such as momentum or a count of commands seen. Every
+/// `Navigator` is also a stateless `StatefulNavigator`.
+pub trait StatefulNavigator<T: Scalar = i32> {
+    fn drive(&mut self, submarine: &mut Submarine<T>, command: &Command) -> Result<(), String>;
+
+    /// Forgets everything remembered from earlier commands.
+    fn reset(&mut self);
+
+    /// A copy of everything the navigator remembers, for `restore` to put back later, or `None`
+    /// if it remembers nothing.
+    fn state(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    /// Puts back a state taken earlier with `state`.
+    fn restore(&mut self, _state: &dyn Any) {}
+
+    /// As `Navigator::kind`. Navigators with state of their own never follow a built in motion.
+    fn kind(&self) -> Option<NavigatorKind> {
+        None
+    }
+
+    fn drive_each<'a>(
+        &mut self,
+        submarine: &mut Submarine<T>,
         commands: impl Iterator<Item = &'a Command>,
-    ) {
-        commands.for_each(|c| self.act_on(submarine, c))
+    ) -> Result<(), String>
+    where
+        Self: Sized,
+    {
+        for (idx, command) in commands.enumerate() {
+            self.drive(submarine, command)
+                .map_err(|e| format!("Command {}: {}", idx, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Scalar, N: Navigator<T>> StatefulNavigator<T> for N {
+    fn drive(&mut self, submarine: &mut Submarine<T>, command: &Command) -> Result<(), String> {
+        self.act_on(submarine, command)
+    }
+
+    fn reset(&mut self) {}
+
+    fn kind(&self) -> Option<NavigatorKind> {
+        Navigator::<T>::kind(self)
     }
 }
 
+// Boxed navigators, as handed out by `crate::registry::Registry`, forward to the navigator in the
+// box. This is implemented for each scalar rather than generically, since a generic impl could
+// overlap with the blanket impl above.
+macro_rules! impl_boxed {
+    ($($t:ty),*) => {
+        $(
+            impl StatefulNavigator<$t> for Box<dyn StatefulNavigator<$t>> {
+                fn drive(
+                    &mut self,
+                    submarine: &mut Submarine<$t>,
+                    command: &Command,
+                ) -> Result<(), String> {
+                    (**self).drive(submarine, command)
+                }
+
+                fn reset(&mut self) {
+                    (**self).reset()
+                }
+
+                fn state(&self) -> Option<Box<dyn Any>> {
+                    (**self).state()
+                }
+
+                fn restore(&mut self, state: &dyn Any) {
+                    (**self).restore(state)
+                }
+
+                fn kind(&self) -> Option<NavigatorKind> {
+                    (**self).kind()
+                }
+            }
+        )*
+    };
+}
+
+impl_boxed!(i32, i64, i128, f64, Fixed);
+
 #[derive(Default)]
 pub struct PartOneNav;
 
@@ -22,14 +149,19 @@ impl PartOneNav {
     }
 }
 
-impl Navigator for PartOneNav {
-    fn act_on(&self, submarine: &mut Submarine, command: &Command) {
+impl<T: Scalar + From<V>, V: Copy> Navigator<T, V> for PartOneNav {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command<V>) -> Result<(), String> {
+        let value = T::from(command.value);
         match command.action {
-            Action::UP => submarine.ascend(command.value),
-            Action::DOWN => submarine.dive(command.value),
-            Action::FORWARD => submarine.forward(command.value),
+            Action::UP => submarine.ascend(value),
+            Action::DOWN => submarine.dive(value),
+            Action::FORWARD => submarine.forward(value),
         }
     }
+
+    fn kind(&self) -> Option<NavigatorKind> {
+        Some(NavigatorKind::PARTONE)
+    }
 }
 
 #[derive(Default)]
@@ -41,15 +173,181 @@ impl AimingNav {
     }
 }
 
-impl Navigator for AimingNav {
-    fn act_on(&self, submarine: &mut Submarine, command: &Command) {
+impl<T: Scalar + From<V>, V: Copy> Navigator<T, V> for AimingNav {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command<V>) -> Result<(), String> {
+        let value = T::from(command.value);
         match command.action {
-            Action::UP => submarine.aim(-command.value),
-            Action::DOWN => submarine.aim(command.value),
+            Action::UP => submarine.aim(submarine.overflow.sub(T::default(), value)?),
+            Action::DOWN => submarine.aim(value),
             Action::FORWARD => {
-                submarine.forward(command.value);
-                submarine.dive(submarine.aim * command.value);
+                // Stage the move on a copy so that an overflow doesn't leave the submarine half
+                // way through a command.
+                let mut next = *submarine;
+                next.forward(value)?;
+                next.dive(submarine.overflow.mul(submarine.aim, value)?)?;
+                *submarine = next;
+                Ok(())
             }
         }
     }
+
+    fn kind(&self) -> Option<NavigatorKind> {
+        Some(NavigatorKind::AIMING)
+    }
+}
+
+/// The motion of each of the built in navigators. Tools that work out what a log does rather than
+/// running it, like `crate::planner`, ask a navigator for its kind with `Navigator::kind`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NavigatorKind {
+    PARTONE,
+    AIMING,
+}
+
+impl NavigatorKind {
+    pub const ALL: [NavigatorKind; 2] = [NavigatorKind::PARTONE, NavigatorKind::AIMING];
+}
+
+impl<T: Scalar + From<V>, V: Copy> Navigator<T, V> for NavigatorKind {
+    fn act_on(&self, submarine: &mut Submarine<T>, command: &Command<V>) -> Result<(), String> {
+        match self {
+            NavigatorKind::PARTONE => PartOneNav::new().act_on(submarine, command),
+            NavigatorKind::AIMING => AimingNav::new().act_on(submarine, command),
+        }
+    }
+
+    fn kind(&self) -> Option<NavigatorKind> {
+        Some(*self)
+    }
+}
+
+#[test]
+fn test_checked_overflow_is_reported() {
+    let commands = Command::parse_each(vec!["down 65536", "forward 1", "forward 65536"]).unwrap();
+
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err("Command 2: Arithmetic overflow: 65536 * 65536".to_string()),
+        AimingNav::new().act_on_each(&mut sub, commands.iter())
+    );
+    assert_eq!(1, sub.x);
+    assert_eq!(65536, sub.depth);
+
+    let mut sub = Submarine::<i64>::default();
+    AimingNav::new()
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!(65537, sub.x);
+    assert_eq!(65536 + (1i64 << 32), sub.depth);
+}
+
+#[test]
+fn test_long_log_overflow() {
+    let commands = Command::parse_each(
+        std::iter::once("down 1000").chain(std::iter::repeat_n("forward 1000", 100_000)),
+    )
+    .unwrap();
+
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err("Command 2148: Arithmetic overflow: 2147000000 + 1000000".to_string()),
+        AimingNav::new().act_on_each(&mut sub, commands.iter())
+    );
+
+    let mut sub = Submarine::<i32>::with_overflow(crate::scalar::Overflow::SATURATING);
+    AimingNav::new()
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!(i32::MAX, sub.depth);
+
+    let mut sub = Submarine::<i128>::default();
+    AimingNav::new()
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!(100_000_000, sub.x);
+    assert_eq!(100_000_000_000, sub.depth);
+}
+
+#[test]
+fn test_navigator_kind() {
+    for kind in NavigatorKind::ALL {
+        assert_eq!(Some(kind), Navigator::<i32>::kind(&kind));
+    }
+    assert_eq!(
+        Some(NavigatorKind::AIMING),
+        StatefulNavigator::<i32>::kind(&Box::new(AimingNav::new()))
+    );
+    let boxed: Box<dyn StatefulNavigator> = Box::new(PartOneNav::new());
+    assert_eq!(Some(NavigatorKind::PARTONE), boxed.kind());
+
+    let commands = Command::parse_each(vec!["down 2", "forward 3"]).unwrap();
+    let mut sub = Submarine::new();
+    NavigatorKind::PARTONE
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!((3, 2, 0), (sub.x, sub.depth, sub.aim));
+
+    let mut sub = Submarine::new();
+    NavigatorKind::AIMING
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!((3, 6, 2), (sub.x, sub.depth, sub.aim));
+}
+
+#[test]
+fn test_fractional_values() {
+    let lines = [
+        "forward 2.5",
+        "down 0.5",
+        "forward 4",
+        "up 1.25",
+        "forward 0.1",
+    ];
+
+    let commands: Vec<Command<f64>> = lines.iter().map(|l| l.parse().unwrap()).collect();
+    let mut sub = Submarine::<f64>::default();
+    AimingNav::new()
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!((6.6, -0.75), (sub.x, sub.aim));
+    assert!((sub.depth - 1.925).abs() < 1e-9);
+
+    // Fixed point keeps decimal fractions exact.
+    let commands: Vec<Command<Fixed>> = lines.iter().map(|l| l.parse().unwrap()).collect();
+    let mut sub = Submarine::<Fixed>::default();
+    AimingNav::new()
+        .act_on_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!(
+        "6.6 1.925 -0.75",
+        format!("{} {} {}", sub.x, sub.depth, sub.aim)
+    );
+}
+
+#[test]
+fn test_whole_metre_logs_are_unchanged() {
+    let lines = vec![
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+    ];
+    let commands = Command::parse_each(lines.clone()).unwrap();
+    let decimal: Vec<Command<f64>> = lines.iter().map(|l| l.parse().unwrap()).collect();
+    for kind in NavigatorKind::ALL {
+        let mut sub = Submarine::new();
+        kind.act_on_each(&mut sub, commands.iter()).unwrap();
+        let mut float = Submarine::<f64>::default();
+        kind.act_on_each(&mut float, decimal.iter()).unwrap();
+        let mut widened = Submarine::<f64>::default();
+        kind.act_on_each(&mut widened, commands.iter()).unwrap();
+        for other in [float, widened] {
+            assert_eq!(
+                (f64::from(sub.x), f64::from(sub.depth), f64::from(sub.aim)),
+                (other.x, other.depth, other.aim)
+            );
+        }
+    }
 }
@@ -0,0 +1,444 @@
+use crate::command::Command;
+use crate::fixed::Fixed;
+use crate::navigator::{NavigatorKind, StatefulNavigator};
+use crate::scalar::Scalar;
+use crate::submarine::Submarine;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::mem;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MutationKind {
+    FORWARD,
+    DIVE,
+    ASCEND,
+    AIM,
+}
+
+/// A call to one of the submarine's mutators. `delta` is the value it was given, so an ascent of 3
+/// is `ASCEND` with a delta of 3, and `up -3` under part one navigation is an ascent of -3.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Mutation<T = i32> {
+    pub kind: MutationKind,
+    pub delta: T,
+    pub before: Submarine<T>,
+    pub after: Submarine<T>,
+}
+
+/// Telemetry attached to a navigator with `Observed`. Every method does nothing by default.
+pub trait Observer<T: Scalar = i32> {
+    /// Called before a command takes effect, with the state it would leave the submarine in.
+    /// Returning an error rejects the command and leaves the submarine as it was.
+    fn check(
+        &self,
+        _command: &Command,
+        _before: &Submarine<T>,
+        _after: &Submarine<T>,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called for each mutation the command made, in the order they were made.
+    fn mutated(&self, _mutation: &Mutation<T>) {}
+
+    /// Called once a command has taken effect.
+    fn acted(&self, _command: &Command, _before: &Submarine<T>, _after: &Submarine<T>) {}
+}
+
+/// Wraps a navigator, stateful or not, so that observers hear about every command it carries out.
+/// The submarine reports each call to its mutators while the command runs, so any navigator can
+/// be observed without changing it, and a move of zero or one that wraps around is reported as it
+/// was made.
+pub struct Observed<'a, N, T = i32> {
+    navigator: N,
+    observers: Vec<&'a dyn Observer<T>>,
+}
+
+impl<'a, N, T: Scalar> Observed<'a, N, T> {
+    pub fn new(navigator: N) -> Observed<'a, N, T> {
+        Observed {
+            navigator,
+            observers: vec![],
+        }
+    }
+
+    pub fn with(mut self, observer: &'a dyn Observer<T>) -> Observed<'a, N, T> {
+        self.observers.push(observer);
+        self
+    }
+}
+
+thread_local! {
+    // The mutations made by each `Observed` navigator that is acting on this thread, innermost
+    // last. Each is a `Vec<Mutation<T>>` for the navigator's scalar.
+    static RECORDINGS: RefCell<Vec<Box<dyn Any>>> = const { RefCell::new(vec![]) };
+    // How many recordings there are, so that mutations made with nothing observing them cost no
+    // more than reading this.
+    static ACTIVE: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Reports a mutation to the innermost `Observed` navigator that is acting, if any.
+pub(crate) fn record<T: Scalar>(
+    kind: MutationKind,
+    delta: T,
+    before: &Submarine<T>,
+    after: &Submarine<T>,
+) {
+    if ACTIVE.get() == 0 {
+        return;
+    }
+    RECORDINGS.with(|recordings| {
+        if let Some(recording) = recordings.borrow_mut().last_mut() {
+            if let Some(mutations) = recording.downcast_mut::<Vec<Mutation<T>>>() {
+                mutations.push(Mutation {
+                    kind,
+                    delta,
+                    before: *before,
+                    after: *after,
+                });
+            }
+        }
+    });
+}
+
+// Collects mutations rather than letting them be reported further out, until it is finished or
+// dropped. Dropping it on the way out of a panic still takes it off the stack, so the recording
+// doesn't swallow mutations meant for another navigator.
+struct Recording<T> {
+    scalar: PhantomData<T>,
+}
+
+impl<T: Scalar> Recording<T> {
+    fn start() -> Recording<T> {
+        RECORDINGS.with(|recordings| {
+            recordings
+                .borrow_mut()
+                .push(Box::new(Vec::<Mutation<T>>::new()))
+        });
+        ACTIVE.set(ACTIVE.get() + 1);
+        Recording {
+            scalar: PhantomData,
+        }
+    }
+
+    fn finish(self) -> Vec<Mutation<T>> {
+        RECORDINGS.with(|recordings| {
+            recordings
+                .borrow_mut()
+                .last_mut()
+                .and_then(|recording| recording.downcast_mut::<Vec<Mutation<T>>>())
+                .map(mem::take)
+                .unwrap_or_default()
+        })
+    }
+}
+
+impl<T> Drop for Recording<T> {
+    fn drop(&mut self) {
+        RECORDINGS.with(|recordings| recordings.borrow_mut().pop());
+        ACTIVE.set(ACTIVE.get() - 1);
+    }
+}
+
+impl<N, T: Scalar> Observed<'_, N, T> {
+    fn drive_observed(
+        &mut self,
+        submarine: &mut Submarine<T>,
+        command: &Command,
+    ) -> Result<(), String>
+    where
+        N: StatefulNavigator<T>,
+    {
+        let mut next = *submarine;
+        let recording = Recording::start();
+        self.navigator.drive(&mut next, command)?;
+        let mutations = recording.finish();
+        for observer in &self.observers {
+            observer.check(command, submarine, &next)?;
+        }
+        // The command has been accepted, so any `Observed` navigator further out hears about it
+        // too.
+        for mutation in &mutations {
+            record(
+                mutation.kind,
+                mutation.delta,
+                &mutation.before,
+                &mutation.after,
+            );
+        }
+        for observer in &self.observers {
+            for mutation in &mutations {
+                observer.mutated(mutation);
+            }
+            observer.acted(command, submarine, &next);
+        }
+        *submarine = next;
+        Ok(())
+    }
+}
+
+// Implemented for each scalar rather than generically, since a generic impl could overlap with the
+// blanket impl for stateless navigators.
+macro_rules! impl_observed {
+    ($($t:ty),*) => {
+        $(
+            impl<N: StatefulNavigator<$t>> StatefulNavigator<$t> for Observed<'_, N, $t> {
+                fn drive(
+                    &mut self,
+                    submarine: &mut Submarine<$t>,
+                    command: &Command,
+                ) -> Result<(), String> {
+                    self.drive_observed(submarine, command)
+                }
+
+                fn reset(&mut self) {
+                    self.navigator.reset();
+                }
+
+                fn state(&self) -> Option<Box<dyn Any>> {
+                    self.navigator.state()
+                }
+
+                fn restore(&mut self, state: &dyn Any) {
+                    self.navigator.restore(state);
+                }
+
+                fn kind(&self) -> Option<NavigatorKind> {
+                    self.navigator.kind()
+                }
+            }
+        )*
+    };
+}
+
+impl_observed!(i32, i64, i128, f64, Fixed);
+
+#[cfg(test)]
+use crate::navigator::AimingNav;
+
+#[cfg(test)]
+#[derive(Default)]
+struct Logger {
+    lines: RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl Observer for Logger {
+    fn mutated(&self, mutation: &Mutation) {
+        self.lines
+            .borrow_mut()
+            .push(format!("  {:?} {}", mutation.kind, mutation.delta));
+    }
+
+    fn acted(&self, command: &Command, _: &Submarine, after: &Submarine) {
+        self.lines
+            .borrow_mut()
+            .push(format!("{} -> {} {}", command, after.x, after.depth));
+    }
+}
+
+#[cfg(test)]
+struct MaxDepth(i32);
+
+#[cfg(test)]
+impl Observer for MaxDepth {
+    fn check(&self, _: &Command, _: &Submarine, after: &Submarine) -> Result<(), String> {
+        if after.depth > self.0 {
+            return Err(format!(
+                "Depth {} is below the limit of {}",
+                after.depth, self.0
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct Plotter {
+    points: RefCell<Vec<(i32, i32)>>,
+}
+
+#[cfg(test)]
+impl Observer for Plotter {
+    fn acted(&self, _: &Command, _: &Submarine, after: &Submarine) {
+        self.points.borrow_mut().push((after.x, after.depth));
+    }
+}
+
+#[test]
+fn test_observers() {
+    let logger = Logger::default();
+    let plotter = Plotter::default();
+    let limit = MaxDepth(50);
+    let mut nav = Observed::new(AimingNav::new())
+        .with(&logger)
+        .with(&limit)
+        .with(&plotter);
+
+    let commands = Command::parse_each(vec![
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+        "up 20",
+        "forward 1",
+    ])
+    .unwrap();
+    let mut sub = Submarine::new();
+    assert_eq!(
+        Err("Command 5: Depth 60 is below the limit of 50".to_string()),
+        nav.drive_each(&mut sub, commands.iter())
+    );
+    assert_eq!((13, 40), (sub.x, sub.depth));
+
+    assert_eq!(
+        vec![
+            "  FORWARD 5",
+            // Aiming navigation dives on every forward, even when level.
+            "  DIVE 0",
+            "forward 5 -> 5 0",
+            "  AIM 5",
+            "down 5 -> 5 0",
+            "  FORWARD 8",
+            "  DIVE 40",
+            "forward 8 -> 13 40",
+            "  AIM -3",
+            "up 3 -> 13 40",
+            "  AIM 8",
+            "down 8 -> 13 40",
+        ],
+        *logger.lines.borrow()
+    );
+    assert_eq!(
+        vec![(5, 0), (5, 0), (13, 40), (13, 40), (13, 40)],
+        *plotter.points.borrow()
+    );
+}
+
+#[test]
+fn test_mutations_as_made() {
+    use crate::environment::{ConstantCurrent, Drifting};
+    use crate::navigator::PartOneNav;
+    use crate::scalar::Overflow;
+
+    let logger = Logger::default();
+    let commands = Command::parse_each(vec!["up -3", "forward 0", "down 0"]).unwrap();
+    let mut sub = Submarine::new();
+    Observed::new(PartOneNav::new())
+        .with(&logger)
+        .drive_each(&mut sub, commands.iter())
+        .unwrap();
+    assert_eq!(
+        vec![
+            "  ASCEND -3",
+            "up -3 -> 0 3",
+            "  FORWARD 0",
+            "forward 0 -> 0 3",
+            "  DIVE 0",
+            "down 0 -> 0 3",
+        ],
+        *logger.lines.borrow()
+    );
+
+    // Drift is a move of its own after the command's.
+    let logger = Logger::default();
+    let mut sub = Submarine::new();
+    Observed::new(Drifting::new(
+        PartOneNav::new(),
+        ConstantCurrent::new(1, -1),
+    ))
+    .with(&logger)
+    .drive(&mut sub, &"down 3".parse().unwrap())
+    .unwrap();
+    assert_eq!(
+        vec!["  DIVE 3", "  FORWARD 1", "  DIVE -1", "down 3 -> 1 2"],
+        *logger.lines.borrow()
+    );
+
+    let logger = Logger::default();
+    let mut sub = Submarine::with_overflow(Overflow::WRAPPING);
+    sub.depth = i32::MAX;
+    Observed::new(PartOneNav::new())
+        .with(&logger)
+        .drive(&mut sub, &"down 1".parse().unwrap())
+        .unwrap();
+    assert_eq!(
+        vec!["  DIVE 1", "down 1 -> 0 -2147483648"],
+        *logger.lines.borrow()
+    );
+}
+
+#[test]
+fn test_nested_observers() {
+    let inner = Logger::default();
+    let outer = Logger::default();
+    let limit = MaxDepth(5);
+    let mut nav =
+        Observed::new(Observed::new(AimingNav::new()).with(&inner).with(&limit)).with(&outer);
+    let mut sub = Submarine::new();
+    nav.drive(&mut sub, &"down 2".parse().unwrap()).unwrap();
+    // Rejected by the inner limit, so neither hears about the mutations.
+    assert!(nav.drive(&mut sub, &"forward 3".parse().unwrap()).is_err());
+    assert_eq!(vec!["  AIM 2", "down 2 -> 0 0"], *inner.lines.borrow());
+    assert_eq!(*inner.lines.borrow(), *outer.lines.borrow());
+}
+
+#[test]
+fn test_panic_leaves_no_recording() {
+    use crate::navigator::Navigator;
+    use std::panic::{self, AssertUnwindSafe};
+
+    struct Panicking;
+
+    impl Navigator for Panicking {
+        fn act_on(&self, submarine: &mut Submarine, _: &Command) -> Result<(), String> {
+            submarine.forward(1)?;
+            panic!("lost at sea");
+        }
+    }
+
+    let mut nav = Observed::new(Panicking);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        nav.drive(&mut Submarine::new(), &"forward 1".parse().unwrap())
+    }));
+    assert!(result.is_err());
+
+    // The abandoned recording would otherwise take these mutations.
+    let logger = Logger::default();
+    let mut nav = Observed::new(AimingNav::new()).with(&logger);
+    nav.drive(&mut Submarine::new(), &"down 1".parse().unwrap())
+        .unwrap();
+    assert_eq!(vec!["  AIM 1", "down 1 -> 0 0"], *logger.lines.borrow());
+}
+
+#[test]
+fn test_stateful_navigators_can_be_observed() {
+    use crate::momentum::Momentum;
+
+    let logger = Logger::default();
+    let mut nav = Observed::new(Momentum::new(1)).with(&logger);
+    let mut sub = Submarine::new();
+    nav.drive_each(
+        &mut sub,
+        Command::parse_each(vec!["forward 3", "down 1"])
+            .unwrap()
+            .iter(),
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            "  FORWARD 3",
+            "forward 3 -> 3 0",
+            "  DIVE 1",
+            "  FORWARD 2",
+            "down 1 -> 5 1",
+        ],
+        *logger.lines.borrow()
+    );
+    nav.reset();
+    assert_eq!(Some(0), nav.state().and_then(|s| s.downcast_ref().copied()));
+}
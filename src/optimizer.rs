@@ -0,0 +1,225 @@
+use crate::command::{Action, Command};
+use crate::navigator::{NavigatorKind, StatefulNavigator};
+
+#[derive(Debug, PartialEq)]
+pub struct Optimized {
+    pub commands: Vec<Command>,
+    pub original_len: usize,
+}
+
+impl Optimized {
+    /// The fraction of commands that were removed, between 0 and 1.
+    pub fn reduction(&self) -> f64 {
+        if self.original_len == 0 {
+            return 0.0;
+        }
+        1.0 - self.commands.len() as f64 / self.original_len as f64
+    }
+}
+
+/// Rewrites `commands` into a sequence that leaves the submarine in the same final state under
+/// `nav`, which must be one of the built in navigators, assuming the original doesn't overflow
+/// along the way.
+///
+/// Under `PARTONE` commands commute, so any log reduces to at most one `forward` and one `up` or
+/// `down`. Under `AIMING` each `forward` dives by the aim in effect at that point, so aim changes
+/// can only be combined with those between the same two forwards. Forwards are combined when
+/// nothing changes the aim between them.
+pub fn optimize(nav: &dyn StatefulNavigator, commands: &[Command]) -> Result<Optimized, String> {
+    let optimized = match nav.kind() {
+        Some(NavigatorKind::PARTONE) => optimize_part_one(commands),
+        Some(NavigatorKind::AIMING) => optimize_aiming(commands),
+        None => return Err("Only the built in navigators can be optimized".to_string()),
+    };
+    Ok(Optimized {
+        commands: optimized,
+        original_len: commands.len(),
+    })
+}
+
+fn vertical(command: &Command) -> i64 {
+    match command.action {
+        Action::UP => -i64::from(command.value),
+        Action::DOWN => i64::from(command.value),
+        Action::FORWARD => 0,
+    }
+}
+
+fn optimize_part_one(commands: &[Command]) -> Vec<Command> {
+    let (mut x, mut depth) = (0, 0);
+    for command in commands {
+        match command.action {
+            Action::FORWARD => x += i64::from(command.value),
+            _ => depth += vertical(command),
+        }
+    }
+    let mut optimized = vec![];
+    push_forward(&mut optimized, x);
+    push_vertical(&mut optimized, depth);
+    optimized
+}
+
+fn optimize_aiming(commands: &[Command]) -> Vec<Command> {
+    let mut optimized = vec![];
+    // The aim the pending forward moves at, and the aim changes since it.
+    let mut aim: i64 = 0;
+    let mut aim_change: i64 = 0;
+    let mut forward: i64 = 0;
+    for command in commands {
+        if command.action != Action::FORWARD {
+            aim_change += vertical(command);
+            continue;
+        }
+        if command.value == 0 {
+            continue;
+        }
+        if aim_change != 0 {
+            push_forward(&mut optimized, forward);
+            push_vertical(&mut optimized, aim_change);
+            aim += aim_change;
+            (forward, aim_change) = (0, 0);
+        }
+        let merged = forward + i64::from(command.value);
+        // Don't let a combined forward overflow where the separate ones wouldn't.
+        let fits = |n: i64| i32::try_from(n).is_ok();
+        if fits(merged) && aim.checked_mul(merged).is_some_and(fits) {
+            forward = merged;
+        } else {
+            push_forward(&mut optimized, forward);
+            forward = i64::from(command.value);
+        }
+    }
+    push_forward(&mut optimized, forward);
+    push_vertical(&mut optimized, aim_change);
+    optimized
+}
+
+fn push_forward(commands: &mut Vec<Command>, mut distance: i64) {
+    let limit = i64::from(i32::MAX);
+    while distance != 0 {
+        let step = distance.clamp(-limit, limit);
+        commands.push(Command::new(Action::FORWARD, step as i32));
+        distance -= step;
+    }
+}
+
+fn push_vertical(commands: &mut Vec<Command>, change: i64) {
+    let action = if change < 0 { Action::UP } else { Action::DOWN };
+    let mut remaining = change.unsigned_abs();
+    while remaining != 0 {
+        let step = remaining.min(i32::MAX as u64);
+        commands.push(Command::new(action, step as i32));
+        remaining -= step;
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::Navigator;
+#[cfg(test)]
+use crate::submarine::Submarine;
+
+#[cfg(test)]
+fn assert_equivalent(nav: NavigatorKind, commands: &[Command]) -> Optimized {
+    let optimized = optimize(&nav, commands).unwrap();
+    let (mut original, mut rewritten) = (Submarine::<i64>::default(), Submarine::<i64>::default());
+    nav.act_on_each(&mut original, commands.iter()).unwrap();
+    nav.act_on_each(&mut rewritten, optimized.commands.iter())
+        .unwrap();
+    assert_eq!(original, rewritten);
+    optimized
+}
+
+#[cfg(test)]
+fn sample() -> Vec<Command> {
+    Command::parse_each(vec![
+        "forward 5",
+        "down 5",
+        "forward 8",
+        "up 3",
+        "down 8",
+        "forward 2",
+    ])
+    .unwrap()
+}
+
+#[test]
+fn test_part_one() {
+    let optimized = assert_equivalent(NavigatorKind::PARTONE, &sample());
+    assert_eq!(
+        Command::parse_each(vec!["forward 15", "down 10"]),
+        Ok(optimized.commands.clone())
+    );
+    assert!((optimized.reduction() - 2.0 / 3.0).abs() < 1e-9);
+
+    let commands = Command::parse_each(vec!["down 3", "forward 0", "up 3"]).unwrap();
+    let optimized = assert_equivalent(NavigatorKind::PARTONE, &commands);
+    assert!(optimized.commands.is_empty());
+    assert_eq!(1.0, optimized.reduction());
+}
+
+#[test]
+fn test_aiming() {
+    let optimized = assert_equivalent(NavigatorKind::AIMING, &sample());
+    assert_eq!(
+        Command::parse_each(vec![
+            "forward 5",
+            "down 5",
+            "forward 8",
+            "down 5",
+            "forward 2"
+        ]),
+        Ok(optimized.commands.clone())
+    );
+    assert!((optimized.reduction() - 1.0 / 6.0).abs() < 1e-9);
+
+    // Aim changes that cancel out let the forwards on either side combine.
+    let commands = Command::parse_each(vec![
+        "forward 5",
+        "down 3",
+        "up 3",
+        "forward 2",
+        "forward 0",
+        "down 4",
+        "up 1",
+    ])
+    .unwrap();
+    assert_eq!(
+        Command::parse_each(vec!["forward 7", "down 3"]),
+        Ok(assert_equivalent(NavigatorKind::AIMING, &commands).commands)
+    );
+}
+
+#[test]
+fn test_totals_beyond_i32() {
+    // Fine for a wider submarine, so the total is split rather than lost.
+    let commands =
+        Command::parse_each(vec!["down 2147483647", "down 2147483647", "down 1"]).unwrap();
+    assert_eq!(
+        Command::parse_each(vec!["down 2147483647", "down 2147483647", "down 1"]),
+        Ok(assert_equivalent(NavigatorKind::PARTONE, &commands).commands)
+    );
+}
+
+#[test]
+fn test_long_log() {
+    let commands = Command::parse_each(include_str!("bin/inputs/problem2.txt").lines()).unwrap();
+    for nav in NavigatorKind::ALL {
+        let optimized = assert_equivalent(nav, &commands);
+        assert!(optimized.reduction() > 0.0);
+    }
+}
+
+#[test]
+fn test_other_navigators() {
+    use crate::momentum::Momentum;
+    use crate::navigator::AimingNav;
+
+    assert_eq!(
+        optimize(&NavigatorKind::AIMING, &sample()),
+        optimize(&AimingNav::new(), &sample())
+    );
+    assert_eq!(
+        Err("Only the built in navigators can be optimized".to_string()),
+        optimize(&Momentum::new(1), &sample())
+    );
+}
@@ -0,0 +1,398 @@
+use crate::command::{Action, Command};
+use crate::navigator::{NavigatorKind, StatefulNavigator};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Constraints {
+    /// Never let the depth go negative, i.e. never rise above the surface.
+    pub never_surface: bool,
+    /// The largest magnitude any single command value may have.
+    pub max_step: Option<i32>,
+    /// Only emit commands with non-negative values.
+    pub non_negative: bool,
+}
+
+impl Constraints {
+    // The number of commands needed to move `distance` in one direction.
+    fn steps(&self, distance: i64) -> i64 {
+        let distance = distance.abs();
+        match (distance, self.max_step) {
+            (0, _) => 0,
+            (_, None) => 1,
+            (_, Some(max)) => (distance + i64::from(max) - 1) / i64::from(max),
+        }
+    }
+
+    // Emits the fewest commands of `action` that add up to `value`.
+    fn split(&self, action: Action, value: i64, out: &mut Vec<Command>) {
+        let max = i64::from(self.max_step.unwrap_or(i32::MAX));
+        let mut remaining = value;
+        while remaining != 0 {
+            let step = remaining.clamp(-max, max);
+            out.push(Command::new(action, step as i32));
+            remaining -= step;
+        }
+    }
+
+    // Emits commands that change depth (or aim) by `delta` without negative values.
+    fn split_vertical(&self, delta: i64, out: &mut Vec<Command>) {
+        if delta < 0 {
+            self.split(Action::UP, -delta, out)
+        } else {
+            self.split(Action::DOWN, delta, out)
+        }
+    }
+
+    fn check_forward(&self, x: i64) -> Result<(), String> {
+        if self.non_negative && x < 0 {
+            return Err(format!("Reaching x {} needs a negative forward command", x));
+        }
+        Ok(())
+    }
+}
+
+/// Plans a shortest command sequence that takes a submarine from the origin to (`x`, `depth`)
+/// under `nav`, which must be one of the built in navigators.
+///
+/// Plans for `PARTONE`, and for `AIMING` without a step limit, are minimal. With a step limit, the
+/// `AIMING` planner searches plans that set the aim at the start of the run and adjust it at most
+/// once more along the way, which takes time linear in the distance travelled.
+pub fn plan(
+    nav: &dyn StatefulNavigator,
+    x: i32,
+    depth: i32,
+    constraints: &Constraints,
+) -> Result<Vec<Command>, String> {
+    let kind = nav
+        .kind()
+        .ok_or_else(|| "Only the built in navigators can be planned for".to_string())?;
+    if let Some(max) = constraints.max_step {
+        if max < 1 {
+            return Err(format!("Invalid max step {}", max));
+        }
+    }
+    if constraints.never_surface && depth < 0 {
+        return Err(format!("Target depth {} is above the surface", depth));
+    }
+    let (x, depth) = (i64::from(x), i64::from(depth));
+    match kind {
+        NavigatorKind::PARTONE => plan_part_one(x, depth, constraints),
+        NavigatorKind::AIMING => plan_aiming(x, depth, constraints),
+    }
+}
+
+// Every part one command moves along exactly one axis, so each axis needs its own commands and
+// the order doesn't matter.
+fn plan_part_one(x: i64, depth: i64, constraints: &Constraints) -> Result<Vec<Command>, String> {
+    constraints.check_forward(x)?;
+    let mut commands = vec![];
+    constraints.split(Action::FORWARD, x, &mut commands);
+    constraints.split_vertical(depth, &mut commands);
+    Ok(commands)
+}
+
+// Under aiming navigation, depth only changes while moving forward. Think of the run as a track
+// of `length` units walked in one direction, with aim changes made at points along it. An aim
+// change of `delta` made with `remaining` units left to go contributes `delta * remaining` to the
+// final depth (scaled by the direction of travel).
+#[derive(Debug)]
+struct Track {
+    length: i64,
+    // (remaining, delta) pairs with strictly decreasing remaining distance.
+    aims: Vec<(i64, i64)>,
+}
+
+impl Track {
+    fn cost(&self, constraints: &Constraints) -> i64 {
+        let mut position = self.length;
+        let mut cost = 0;
+        for &(remaining, delta) in &self.aims {
+            cost += constraints.steps(position - remaining) + constraints.steps(delta);
+            position = remaining;
+        }
+        cost + constraints.steps(position)
+    }
+
+    fn emit(&self, direction: i64, constraints: &Constraints, out: &mut Vec<Command>) {
+        let mut position = self.length;
+        for &(remaining, delta) in &self.aims {
+            constraints.split(Action::FORWARD, direction * (position - remaining), out);
+            constraints.split_vertical(delta, out);
+            position = remaining;
+        }
+        constraints.split(Action::FORWARD, direction * position, out);
+    }
+}
+
+fn plan_aiming(x: i64, depth: i64, constraints: &Constraints) -> Result<Vec<Command>, String> {
+    constraints.check_forward(x)?;
+    let mut commands = vec![];
+    if depth == 0 {
+        constraints.split(Action::FORWARD, x, &mut commands);
+        return Ok(commands);
+    }
+
+    if x == 0 {
+        // Depth can only be gained by moving, so back up and then come forward again.
+        constraints.check_forward(-1)?;
+        let (backup, track) = back_and_forth_track(depth, constraints)?;
+        constraints.split(Action::FORWARD, -backup, &mut commands);
+        track.emit(1, constraints, &mut commands);
+        return Ok(commands);
+    }
+
+    let direction = x.signum();
+    let track = best_track(x.abs(), direction * depth, direction, constraints)
+        .ok_or_else(|| format!("No plan reaches x {} depth {}", x, depth))?;
+    track.emit(direction, constraints, &mut commands);
+    Ok(commands)
+}
+
+fn back_and_forth_track(depth: i64, constraints: &Constraints) -> Result<(i64, Track), String> {
+    let mut best: Option<(i64, i64, Track)> = None;
+    let mut backup = 1;
+    // Backing up `backup` units and returning costs at least twice the forwards, so stop once
+    // that alone is no better than the best plan so far.
+    while best
+        .as_ref()
+        .is_none_or(|(cost, _, _)| 2 * constraints.steps(backup) < *cost)
+    {
+        if let Some(track) = best_track(backup, depth, 1, constraints) {
+            let cost = constraints.steps(backup) + track.cost(constraints);
+            if best
+                .as_ref()
+                .is_none_or(|(best_cost, _, _)| cost < *best_cost)
+            {
+                best = Some((cost, backup, track));
+            }
+        }
+        if constraints.max_step.is_none() || backup >= depth.abs() {
+            break;
+        }
+        backup += 1;
+    }
+    best.map(|(_, backup, track)| (backup, track))
+        .ok_or_else(|| format!("No plan reaches x 0 depth {}", depth))
+}
+
+// Finds the cheapest track of `length` units whose aim changes add up to `target` depth.
+fn best_track(
+    length: i64,
+    target: i64,
+    direction: i64,
+    constraints: &Constraints,
+) -> Option<Track> {
+    // Without a step limit, one aim change is always enough: at the start if it divides evenly,
+    // otherwise with a single unit to go.
+    if constraints.max_step.is_none() {
+        let remaining = if target % length == 0 { length } else { 1 };
+        return Some(Track {
+            length,
+            aims: vec![(remaining, target / remaining)],
+        });
+    }
+
+    let mut best: Option<(i64, Track)> = None;
+    let mut consider = |track: Track| {
+        let cost = track.cost(constraints);
+        if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+            best = Some((cost, track));
+        }
+    };
+
+    for remaining in 1..=length {
+        if target % remaining == 0 {
+            consider(Track {
+                length,
+                aims: vec![(remaining, target / remaining)],
+            });
+        }
+    }
+
+    for remaining in 1..length {
+        if let Some((start, later)) = two_aims(length, remaining, target, direction, constraints) {
+            consider(Track {
+                length,
+                aims: vec![(length, start), (remaining, later)],
+            });
+        }
+    }
+
+    best.map(|(_, track)| track)
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// Solves `start * length + later * remaining == target` for the cheapest pair of aim changes.
+fn two_aims(
+    length: i64,
+    remaining: i64,
+    target: i64,
+    direction: i64,
+    constraints: &Constraints,
+) -> Option<(i64, i64)> {
+    let (a, b, t) = (
+        i128::from(length),
+        i128::from(remaining),
+        i128::from(target),
+    );
+    let (g, x, y) = extended_gcd(a, b);
+    if t % g != 0 {
+        return None;
+    }
+    let (start0, later0) = (x * (t / g), y * (t / g));
+    let (start_step, later_step) = (b / g, a / g);
+
+    // The cost is smallest near where either aim change vanishes.
+    let roots = [-start0 / start_step, later0 / later_step];
+    roots
+        .iter()
+        .flat_map(|&k| k - 2..=k + 2)
+        .map(|k| (start0 + k * start_step, later0 - k * later_step))
+        .filter(|&(start, later)| start != 0 && later != 0)
+        // The depth is piecewise linear, so it only goes negative if the first leg heads up.
+        .filter(|&(start, _)| !constraints.never_surface || start * i128::from(direction) > 0)
+        .filter_map(|(start, later)| Some((i64::try_from(start).ok()?, i64::try_from(later).ok()?)))
+        .min_by_key(|&(start, later)| constraints.steps(start) + constraints.steps(later))
+}
+
+#[cfg(test)]
+use crate::navigator::{AimingNav, PartOneNav};
+
+#[cfg(test)]
+fn fly(nav: &dyn StatefulNavigator, commands: &[Command], constraints: &Constraints) -> (i32, i32) {
+    use crate::navigator::Navigator;
+    use crate::submarine::Submarine;
+
+    let nav = nav.kind().unwrap();
+    let mut sub = Submarine::new();
+    for command in commands {
+        if let Some(max) = constraints.max_step {
+            assert!(command.value.abs() <= max, "{} is too big", command);
+        }
+        if constraints.non_negative {
+            assert!(command.value >= 0, "{} is negative", command);
+        }
+        nav.act_on(&mut sub, command).unwrap();
+        if constraints.never_surface {
+            assert!(sub.depth >= 0, "{} surfaced", command);
+        }
+    }
+    (sub.x, sub.depth)
+}
+
+#[cfg(test)]
+fn check_plan(
+    nav: &dyn StatefulNavigator,
+    x: i32,
+    depth: i32,
+    constraints: Constraints,
+    expected_len: usize,
+) -> Vec<Command> {
+    let commands = plan(nav, x, depth, &constraints).unwrap();
+    assert_eq!((x, depth), fly(nav, &commands, &constraints));
+    assert_eq!(expected_len, commands.len(), "{:?}", commands);
+    commands
+}
+
+#[test]
+fn test_part_one() {
+    let unconstrained = Constraints::default();
+    assert_eq!(
+        Command::parse_each(vec!["forward 15", "down 10"]),
+        Ok(check_plan(&PartOneNav, 15, 10, unconstrained, 2))
+    );
+    assert_eq!(
+        Command::parse_each(vec!["forward -3", "up 4"]),
+        Ok(check_plan(&PartOneNav, -3, -4, unconstrained, 2))
+    );
+    check_plan(&PartOneNav, 0, 0, unconstrained, 0);
+
+    let limited = Constraints {
+        max_step: Some(4),
+        ..Constraints::default()
+    };
+    assert_eq!(
+        Command::parse_each(vec!["forward 4", "forward 4", "forward 2", "down 3"]),
+        Ok(check_plan(&PartOneNav, 10, 3, limited, 4))
+    );
+}
+
+#[test]
+fn test_aiming_unlimited() {
+    let unconstrained = Constraints::default();
+    assert_eq!(
+        Command::parse_each(vec!["down 4", "forward 15"]),
+        Ok(check_plan(&AimingNav, 15, 60, unconstrained, 2))
+    );
+    assert_eq!(
+        Command::parse_each(vec!["forward 6", "down 10", "forward 1"]),
+        Ok(check_plan(&AimingNav, 7, 10, unconstrained, 3))
+    );
+    check_plan(&AimingNav, -7, 10, unconstrained, 3);
+    check_plan(&AimingNav, -5, -10, unconstrained, 2);
+    check_plan(&AimingNav, 9, 0, unconstrained, 1);
+    assert_eq!(
+        Command::parse_each(vec!["forward -1", "down 8", "forward 1"]),
+        Ok(check_plan(&AimingNav, 0, 8, unconstrained, 3))
+    );
+}
+
+#[test]
+fn test_aiming_limited() {
+    let limited = Constraints {
+        max_step: Some(10),
+        ..Constraints::default()
+    };
+    check_plan(&AimingNav, 1000, 5000, limited, 101);
+    check_plan(&AimingNav, 1000, 5003, limited, 103);
+    check_plan(&AimingNav, 5, 1000, limited, 21);
+    check_plan(&AimingNav, 0, 100, limited, 3);
+}
+
+#[test]
+fn test_constraints() {
+    let careful = Constraints {
+        never_surface: true,
+        max_step: Some(5),
+        non_negative: true,
+    };
+    check_plan(&PartOneNav, 12, 7, careful, 5);
+    check_plan(&AimingNav, 12, 7, careful, 4);
+    check_plan(&AimingNav, 12, 31, careful, 5);
+
+    assert_eq!(
+        Err("Target depth -1 is above the surface".to_string()),
+        plan(&AimingNav, 5, -1, &careful)
+    );
+    assert_eq!(
+        Err("Reaching x -5 needs a negative forward command".to_string()),
+        plan(&PartOneNav, -5, 1, &careful)
+    );
+    assert_eq!(
+        Err("Reaching x -1 needs a negative forward command".to_string()),
+        plan(&AimingNav, 0, 1, &careful)
+    );
+    assert_eq!(
+        Err("Only the built in navigators can be planned for".to_string()),
+        plan(&crate::momentum::Momentum::new(1), 3, 3, &careful)
+    );
+    assert_eq!(
+        Err("Invalid max step 0".to_string()),
+        plan(
+            &AimingNav,
+            0,
+            1,
+            &Constraints {
+                max_step: Some(0),
+                ..Constraints::default()
+            }
+        )
+    );
+}
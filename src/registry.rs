@@ -0,0 +1,160 @@
+use crate::navigator::{AimingNav, PartOneNav, StatefulNavigator};
+use crate::scalar::Scalar;
+
+type Factory<T> = Box<dyn Fn() -> Box<dyn StatefulNavigator<T>>>;
+
+/// Navigators that can be looked up by name, so that the mode can come from the command line or a
+/// config file. Stateful navigators such as `crate::momentum::Momentum` can be registered as well
+/// as plain ones.
+pub struct Registry<T = i32> {
+    // In registration order, which is the order names are listed in.
+    factories: Vec<(String, Factory<T>)>,
+}
+
+impl<T: Scalar + 'static> Registry<T> {
+    /// An empty registry. Use `builtin` for one that already holds the built in navigators.
+    pub fn new() -> Registry<T> {
+        Registry { factories: vec![] }
+    }
+
+    /// A registry holding the built in navigators, `part1` and `aiming`.
+    pub fn builtin() -> Registry<T> {
+        let mut registry = Registry::new();
+        registry.register("part1", PartOneNav::new).unwrap();
+        registry.register("aiming", AimingNav::new).unwrap();
+        registry
+    }
+
+    pub fn register<N: StatefulNavigator<T> + 'static>(
+        &mut self,
+        name: &str,
+        factory: impl Fn() -> N + 'static,
+    ) -> Result<(), String> {
+        if self.factories.iter().any(|(n, _)| n == name) {
+            return Err(format!("Navigator \"{}\" is already registered", name));
+        }
+        self.factories.push((
+            name.to_string(),
+            Box::new(move || Box::new(factory()) as Box<dyn StatefulNavigator<T>>),
+        ));
+        Ok(())
+    }
+
+    /// A new instance of the navigator registered as `name`, with no state of its own yet.
+    pub fn get(&self, name: &str) -> Result<Box<dyn StatefulNavigator<T>>, String> {
+        self.factories
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, factory)| factory())
+            .ok_or_else(|| format!("Unknown navigator \"{}\"", name))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+impl<T: Scalar + 'static> Default for Registry<T> {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+#[cfg(test)]
+use crate::command::Command;
+#[cfg(test)]
+use crate::fuel::{Fueled, StandardCost};
+#[cfg(test)]
+use crate::geofence::{Geofenced, Zone};
+#[cfg(test)]
+use crate::momentum::Momentum;
+#[cfg(test)]
+use crate::submarine::Submarine;
+
+#[test]
+fn test_builtin() {
+    let registry = Registry::builtin();
+    assert_eq!(
+        vec!["part1", "aiming"],
+        registry.names().collect::<Vec<_>>()
+    );
+
+    let commands = Command::parse_each(vec!["down 2", "forward 3"]).unwrap();
+    let mut results = vec![];
+    for name in registry.names() {
+        let mut sub = Submarine::new();
+        registry
+            .get(name)
+            .unwrap()
+            .drive_each(&mut sub, commands.iter())
+            .unwrap();
+        results.push((sub.x, sub.depth));
+    }
+    assert_eq!(vec![(3, 2), (3, 6)], results);
+
+    assert_eq!(
+        Some("Unknown navigator \"sideways\"".to_string()),
+        registry.get("sideways").err()
+    );
+    assert_eq!(0, Registry::<i32>::default().names().count());
+}
+
+#[test]
+fn test_register() {
+    let mut registry = Registry::<i64>::builtin();
+    registry
+        .register("thrifty", || {
+            Fueled::new(AimingNav::new(), StandardCost::new(1, 0))
+        })
+        .unwrap();
+    assert_eq!(
+        Err("Navigator \"aiming\" is already registered".to_string()),
+        registry.register("aiming", PartOneNav::new)
+    );
+
+    let mut navigators: Vec<Box<dyn StatefulNavigator<i64>>> =
+        registry.names().map(|n| registry.get(n).unwrap()).collect();
+    assert_eq!(3, navigators.len());
+    let mut sub = Submarine::with_fuel(4);
+    assert_eq!(
+        Err("Command 1: Out of fuel: \"forward 5\" needs 10 but only 4 is left".to_string()),
+        navigators[2].drive_each(
+            &mut sub,
+            Command::parse_each(vec!["down 1", "forward 5"])
+                .unwrap()
+                .iter()
+        )
+    );
+}
+
+#[test]
+fn test_register_stateful() {
+    let mut registry = Registry::new();
+    registry.register("coasting", || Momentum::new(1)).unwrap();
+    registry
+        .register("fenced", || {
+            Geofenced::new(
+                PartOneNav::new(),
+                vec![Zone::rectangle("cable", (5, 0), (6, 10)).forbidden()],
+            )
+            .halting()
+        })
+        .unwrap();
+    let commands = Command::parse_each(vec!["forward 2", "down 1", "down 1"]).unwrap();
+
+    // Each instance starts with no speed of its own, and coasts 2 + 1 + 0.
+    for _ in 0..2 {
+        let mut sub = Submarine::new();
+        let mut nav = registry.get("coasting").unwrap();
+        nav.drive_each(&mut sub, commands.iter()).unwrap();
+        assert_eq!((3, 2), (sub.x, sub.depth));
+    }
+
+    let mut nav = registry.get("fenced").unwrap();
+    let mut sub = Submarine::new();
+    let forward = Command::parse_each(vec!["forward 3", "forward 3"]).unwrap();
+    assert_eq!(
+        Err("Command 1: Entered forbidden zone \"cable\"".to_string()),
+        nav.drive_each(&mut sub, forward.iter())
+    );
+}
@@ -0,0 +1,205 @@
+use std::fmt::{self, Debug, Display};
+use std::str::FromStr;
+
+pub trait Scalar:
+    Copy + Debug + Default + Display + PartialEq + PartialOrd + From<i32> + 'static
+{
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    fn saturating_mul(self, rhs: Self) -> Self;
+
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_integer_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, rhs)
+                }
+
+                fn saturating_add(self, rhs: Self) -> Self {
+                    <$t>::saturating_add(self, rhs)
+                }
+
+                fn saturating_sub(self, rhs: Self) -> Self {
+                    <$t>::saturating_sub(self, rhs)
+                }
+
+                fn saturating_mul(self, rhs: Self) -> Self {
+                    <$t>::saturating_mul(self, rhs)
+                }
+
+                fn wrapping_add(self, rhs: Self) -> Self {
+                    <$t>::wrapping_add(self, rhs)
+                }
+
+                fn wrapping_sub(self, rhs: Self) -> Self {
+                    <$t>::wrapping_sub(self, rhs)
+                }
+
+                fn wrapping_mul(self, rhs: Self) -> Self {
+                    <$t>::wrapping_mul(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_scalar!(i32, i64, i128);
+
+/// Checked arithmetic on floating point numbers fails when the result isn't finite, saturating
+/// arithmetic stops at the largest finite values, and wrapping arithmetic is plain floating point
+/// arithmetic, which overflows to infinity.
+impl Scalar for f64 {
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        Some(self + rhs).filter(|r| r.is_finite())
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        Some(self - rhs).filter(|r| r.is_finite())
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Some(self * rhs).filter(|r| r.is_finite())
+    }
+
+    fn saturating_add(self, rhs: Self) -> Self {
+        (self + rhs).clamp(f64::MIN, f64::MAX)
+    }
+
+    fn saturating_sub(self, rhs: Self) -> Self {
+        (self - rhs).clamp(f64::MIN, f64::MAX)
+    }
+
+    fn saturating_mul(self, rhs: Self) -> Self {
+        (self * rhs).clamp(f64::MIN, f64::MAX)
+    }
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        self + rhs
+    }
+
+    fn wrapping_sub(self, rhs: Self) -> Self {
+        self - rhs
+    }
+
+    fn wrapping_mul(self, rhs: Self) -> Self {
+        self * rhs
+    }
+}
+
+/// How a `Submarine` handles arithmetic that doesn't fit in its scalar type. Widening is achieved
+/// by choosing a wider scalar (`Submarine<i64>`, `Submarine<i128>`) rather than a different mode.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Overflow {
+    #[default]
+    CHECKED,
+    SATURATING,
+    WRAPPING,
+}
+
+impl Overflow {
+    pub fn add<T: Scalar>(self, lhs: T, rhs: T) -> Result<T, String> {
+        match self {
+            Overflow::CHECKED => lhs
+                .checked_add(rhs)
+                .ok_or_else(|| format!("Arithmetic overflow: {} + {}", lhs, rhs)),
+            Overflow::SATURATING => Ok(lhs.saturating_add(rhs)),
+            Overflow::WRAPPING => Ok(lhs.wrapping_add(rhs)),
+        }
+    }
+
+    pub fn sub<T: Scalar>(self, lhs: T, rhs: T) -> Result<T, String> {
+        match self {
+            Overflow::CHECKED => lhs
+                .checked_sub(rhs)
+                .ok_or_else(|| format!("Arithmetic overflow: {} - {}", lhs, rhs)),
+            Overflow::SATURATING => Ok(lhs.saturating_sub(rhs)),
+            Overflow::WRAPPING => Ok(lhs.wrapping_sub(rhs)),
+        }
+    }
+
+    pub fn mul<T: Scalar>(self, lhs: T, rhs: T) -> Result<T, String> {
+        match self {
+            Overflow::CHECKED => lhs
+                .checked_mul(rhs)
+                .ok_or_else(|| format!("Arithmetic overflow: {} * {}", lhs, rhs)),
+            Overflow::SATURATING => Ok(lhs.saturating_mul(rhs)),
+            Overflow::WRAPPING => Ok(lhs.wrapping_mul(rhs)),
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Overflow, String> {
+        match name {
+            "checked" => Ok(Overflow::CHECKED),
+            "saturating" => Ok(Overflow::SATURATING),
+            "wrapping" => Ok(Overflow::WRAPPING),
+            _ => Err(format!("Unknown overflow mode \"{}\"", name)),
+        }
+    }
+}
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Overflow::CHECKED => "checked",
+            Overflow::SATURATING => "saturating",
+            Overflow::WRAPPING => "wrapping",
+        })
+    }
+}
+
+#[test]
+fn test_overflow_modes() {
+    assert_eq!(Ok(3), Overflow::CHECKED.add(1, 2));
+    assert_eq!(
+        Err("Arithmetic overflow: 2147483647 + 1".to_string()),
+        Overflow::CHECKED.add(i32::MAX, 1)
+    );
+    assert_eq!(
+        Err("Arithmetic overflow: -2147483648 - 1".to_string()),
+        Overflow::CHECKED.sub(i32::MIN, 1)
+    );
+    assert_eq!(
+        Err("Arithmetic overflow: 65536 * 65536".to_string()),
+        Overflow::CHECKED.mul(65536, 65536)
+    );
+
+    assert_eq!(Ok(i32::MAX), Overflow::SATURATING.add(i32::MAX, 1));
+    assert_eq!(Ok(i32::MIN), Overflow::SATURATING.sub(i32::MIN, 1));
+    assert_eq!(Ok(i32::MAX), Overflow::SATURATING.mul(65536, 65536));
+
+    assert_eq!(Ok(i32::MIN), Overflow::WRAPPING.add(i32::MAX, 1));
+    assert_eq!(Ok(i32::MAX), Overflow::WRAPPING.sub(i32::MIN, 1));
+    assert_eq!(Ok(0), Overflow::WRAPPING.mul(65536, 65536));
+
+    assert_eq!(Ok(1i64 << 32), Overflow::CHECKED.mul(65536i64, 65536i64));
+
+    assert_eq!(Ok(2.5), Overflow::CHECKED.add(1.25, 1.25));
+    assert_eq!(
+        Err(format!("Arithmetic overflow: {} * 2", f64::MAX)),
+        Overflow::CHECKED.mul(f64::MAX, 2.0)
+    );
+    assert_eq!(Ok(f64::MAX), Overflow::SATURATING.mul(f64::MAX, 2.0));
+    assert_eq!(Ok(f64::INFINITY), Overflow::WRAPPING.mul(f64::MAX, 2.0));
+}
@@ -0,0 +1,649 @@
+use crate::command::{Action, Command};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The most commands a script may expand to, so that a runaway `repeat` fails instead of
+/// exhausting memory.
+pub const MAX_COMMANDS: usize = 1_000_000;
+
+/// The most statements and loop iterations a script may run, so that a `repeat` that produces no
+/// commands still finishes.
+pub const MAX_STEPS: usize = 10_000_000;
+
+/// The deepest includes may nest.
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
+// Mission scripts are line oriented:
+//
+//     # Comments run to the end of the line.
+//     let leg = 5
+//     macro zigzag {
+//         down 2
+//         forward leg * 2
+//         up 2
+//     }
+//     repeat 3 {
+//         zigzag
+//         let leg = leg + 1
+//     }
+//     include "return_home.mis"
+//
+// Expanding a script produces the plain `Command` stream it describes.
+
+#[derive(Clone, Debug)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Literal(i32),
+    Variable(String),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, Operator, Box<Expr>),
+}
+
+#[derive(Clone, Debug)]
+enum Statement {
+    Command(Action, Expr),
+    Let(String, Expr),
+    Repeat(Expr, Vec<Line>),
+    Macro(String, Vec<Line>),
+    Call(String),
+    Include(String),
+}
+
+#[derive(Clone, Debug)]
+struct Line {
+    number: usize,
+    statement: Statement,
+}
+
+const KEYWORDS: [&str; 7] = ["let", "repeat", "macro", "include", "up", "down", "forward"];
+
+fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_identifier(word: &str) -> Result<String, String> {
+    if !is_identifier(word) || KEYWORDS.contains(&word) {
+        return Err(format!("Invalid name \"{}\"", word));
+    }
+    Ok(word.to_string())
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(i32),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(d);
+                chars.next();
+            }
+            let value = digits
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid number \"{}\"", digits))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&d) = chars
+                .peek()
+                .filter(|d| d.is_ascii_alphanumeric() || **d == '_')
+            {
+                name.push(d);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("Unexpected character '{}' in expression", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl ExprParser {
+    fn parse(text: &str) -> Result<Expr, String> {
+        let mut parser = ExprParser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err("Missing expression".to_string());
+        }
+        let expr = parser.sum()?;
+        if parser.position != parser.tokens.len() {
+            return Err(format!("Invalid expression \"{}\"", text));
+        }
+        Ok(expr)
+    }
+
+    fn peek_symbol(&self) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek_symbol() {
+            self.position += 1;
+            let operator = if op == '+' {
+                Operator::Add
+            } else {
+                Operator::Subtract
+            };
+            lhs = Expr::Binary(Box::new(lhs), operator, Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek_symbol() {
+            self.position += 1;
+            let operator = if op == '*' {
+                Operator::Multiply
+            } else {
+                Operator::Divide
+            };
+            lhs = Expr::Binary(Box::new(lhs), operator, Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek_symbol() == Some('-') {
+            self.position += 1;
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        match token {
+            Some(Token::Number(n)) => Ok(Expr::Literal(*n)),
+            Some(Token::Name(name)) => Ok(Expr::Variable(name.clone())),
+            Some(Token::Symbol('(')) => {
+                let expr = self.sum()?;
+                if self.peek_symbol() != Some(')') {
+                    return Err("Expected ')'".to_string());
+                }
+                self.position += 1;
+                Ok(expr)
+            }
+            Some(Token::Symbol(c)) => Err(format!("Unexpected '{}' in expression", c)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+fn block_header<'a>(rest: &'a str, keyword: &str) -> Result<&'a str, String> {
+    rest.strip_suffix('{')
+        .map(str::trim)
+        .ok_or_else(|| format!("Expected \"{{\" at the end of {}", keyword))
+}
+
+fn parse_block<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    opened_at: Option<usize>,
+) -> Result<Vec<Line>, String> {
+    let mut block = vec![];
+    while let Some((number, raw)) = lines.next() {
+        let text = strip_comment(raw).trim();
+        if text.is_empty() {
+            continue;
+        }
+        if text == "}" {
+            return match opened_at {
+                Some(_) => Ok(block),
+                None => Err(format!("Line {}: Unexpected \"}}\"", number)),
+            };
+        }
+        let mut statement = parse_statement(text).map_err(|e| format!("Line {}: {}", number, e))?;
+        if let Statement::Repeat(_, body) | Statement::Macro(_, body) = &mut statement {
+            *body = parse_block(lines, Some(number))?;
+        }
+        block.push(Line { number, statement });
+    }
+    match opened_at {
+        Some(number) => Err(format!("Line {}: Block is never closed", number)),
+        None => Ok(block),
+    }
+}
+
+// Parses a single line. The bodies of block statements are filled in by the caller.
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (keyword, rest) = match text.split_once(char::is_whitespace) {
+        Some((keyword, rest)) => (keyword, rest.trim()),
+        None => (text, ""),
+    };
+    match keyword {
+        "let" => {
+            let (name, value) = rest
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid assignment \"{}\"", text))?;
+            Ok(Statement::Let(
+                parse_identifier(name.trim())?,
+                ExprParser::parse(value)?,
+            ))
+        }
+        "repeat" => {
+            let count = ExprParser::parse(block_header(rest, keyword)?)?;
+            Ok(Statement::Repeat(count, vec![]))
+        }
+        "macro" => {
+            let name = parse_identifier(block_header(rest, keyword)?)?;
+            Ok(Statement::Macro(name, vec![]))
+        }
+        "include" => {
+            let name = rest.trim_matches('"');
+            if name.is_empty() {
+                return Err("Missing include path".to_string());
+            }
+            Ok(Statement::Include(name.to_string()))
+        }
+        _ if rest.is_empty() && is_identifier(keyword) => Ok(Statement::Call(keyword.to_string())),
+        _ => Ok(Statement::Command(
+            keyword.parse::<Action>()?,
+            ExprParser::parse(rest)?,
+        )),
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Line>, String> {
+    parse_block(
+        &mut source.lines().enumerate().map(|(i, l)| (i + 1, l)),
+        None,
+    )
+}
+
+struct Expander<'a> {
+    loader: &'a dyn Fn(&str) -> Result<String, String>,
+    variables: HashMap<String, i32>,
+    macros: HashMap<String, Vec<Line>>,
+    // Macros and includes currently being expanded, used to reject recursion.
+    active: Vec<String>,
+    // The includes currently being expanded, innermost last, to resolve nested includes against.
+    files: Vec<String>,
+    commands: Vec<Command>,
+    // Statements and loop iterations run so far.
+    steps: usize,
+}
+
+impl<'a> Expander<'a> {
+    fn evaluate(&self, expr: &Expr) -> Result<i32, String> {
+        match expr {
+            Expr::Literal(n) => Ok(*n),
+            Expr::Variable(name) => self
+                .variables
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("Undefined variable \"{}\"", name)),
+            Expr::Negate(inner) => self
+                .evaluate(inner)?
+                .checked_neg()
+                .ok_or_else(|| "Arithmetic overflow".to_string()),
+            Expr::Binary(lhs, operator, rhs) => {
+                let lhs = self.evaluate(lhs)?;
+                let rhs = self.evaluate(rhs)?;
+                match operator {
+                    Operator::Add => lhs.checked_add(rhs),
+                    Operator::Subtract => lhs.checked_sub(rhs),
+                    Operator::Multiply => lhs.checked_mul(rhs),
+                    Operator::Divide if rhs == 0 => return Err("Division by zero".to_string()),
+                    Operator::Divide => lhs.checked_div(rhs),
+                }
+                .ok_or_else(|| "Arithmetic overflow".to_string())
+            }
+        }
+    }
+
+    fn run(&mut self, block: &[Line]) -> Result<(), String> {
+        for line in block {
+            self.run_statement(&line.statement)
+                .map_err(|e| format!("Line {}: {}", line.number, e))?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        if self.steps == MAX_STEPS {
+            return Err(format!("Script runs for more than {} steps", MAX_STEPS));
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn run_statement(&mut self, statement: &Statement) -> Result<(), String> {
+        self.step()?;
+        match statement {
+            Statement::Command(action, expr) => {
+                let value = self.evaluate(expr)?;
+                if self.commands.len() == MAX_COMMANDS {
+                    return Err(format!(
+                        "Script expands to more than {} commands",
+                        MAX_COMMANDS
+                    ));
+                }
+                self.commands.push(Command::new(*action, value));
+            }
+            Statement::Let(name, expr) => {
+                let value = self.evaluate(expr)?;
+                self.variables.insert(name.clone(), value);
+            }
+            Statement::Repeat(count, body) => {
+                let count = self.evaluate(count)?;
+                if count < 0 {
+                    return Err(format!("Invalid repeat count {}", count));
+                }
+                for _ in 0..count {
+                    self.step()?;
+                    self.run(body)?;
+                }
+            }
+            Statement::Macro(name, body) => {
+                self.macros.insert(name.clone(), body.clone());
+            }
+            Statement::Call(name) => {
+                let body = self
+                    .macros
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("Undefined macro \"{}\"", name))?;
+                self.enter(name)?;
+                self.run(&body)
+                    .map_err(|e| format!("In macro \"{}\": {}", name, e))?;
+                self.active.pop();
+            }
+            Statement::Include(name) => {
+                if self.files.len() == MAX_INCLUDE_DEPTH {
+                    return Err(format!(
+                        "Includes nest more than {} deep",
+                        MAX_INCLUDE_DEPTH
+                    ));
+                }
+                // Nested includes are relative to the script that includes them.
+                let name = match self.files.last() {
+                    Some(file) => normalize(&Path::new(file).with_file_name(name)),
+                    None => normalize(Path::new(name)),
+                };
+                let source = (self.loader)(&name)?;
+                let block = parse(&source).map_err(|e| format!("In \"{}\": {}", name, e))?;
+                self.enter(&name)?;
+                self.files.push(name.clone());
+                self.run(&block)
+                    .map_err(|e| format!("In \"{}\": {}", name, e))?;
+                self.files.pop();
+                self.active.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, name: &str) -> Result<(), String> {
+        if self.active.iter().any(|n| n == name) {
+            return Err(format!("\"{}\" is expanded recursively", name));
+        }
+        self.active.push(name.to_string());
+        Ok(())
+    }
+}
+
+// Resolves "." and ".." in `path` without touching the file system, so that the same script
+// reached by two routes has one name.
+fn normalize(path: &Path) -> String {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(normal.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+    normal.display().to_string()
+}
+
+/// Expands a mission script into commands. `loader` returns the source of any script named by an
+/// `include`. An include inside an included script is named relative to that script, so
+/// `include "b.mis"` in `sub/a.mis` asks for `sub/b.mis`.
+pub fn expand(
+    source: &str,
+    loader: impl Fn(&str) -> Result<String, String>,
+) -> Result<Vec<Command>, String> {
+    let block = parse(source)?;
+    let mut expander = Expander {
+        loader: &loader,
+        variables: HashMap::new(),
+        macros: HashMap::new(),
+        active: vec![],
+        files: vec![],
+        commands: vec![],
+        steps: 0,
+    };
+    expander.run(&block)?;
+    Ok(expander.commands)
+}
+
+/// Expands the mission script at `path`. Includes are resolved relative to the directory of the
+/// script containing them.
+pub fn expand_file(path: impl AsRef<Path>) -> Result<Vec<Command>, String> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let read = |p: &Path| {
+        fs::read_to_string(p).map_err(|e| format!("Could not read {}: {}", p.display(), e))
+    };
+    expand(&read(path)?, |name| read(&dir.join(name)))
+}
+
+#[cfg(test)]
+fn no_includes(name: &str) -> Result<String, String> {
+    Err(format!("No such script \"{}\"", name))
+}
+
+#[test]
+fn test_plain_commands_and_comments() {
+    assert_eq!(
+        Command::parse_each(vec!["forward 5", "down 3", "up -1"]),
+        expand(
+            "# Leave port\nforward 5  # Clear the harbour\n\n  down 3\nup -1\n",
+            no_includes
+        )
+    );
+    assert_eq!(Ok(vec![]), expand("# Nothing to do\n", no_includes));
+}
+
+#[test]
+fn test_variables() {
+    assert_eq!(
+        Command::parse_each(vec!["forward 7", "down 14", "up -3", "forward 8"]),
+        expand(
+            "let a = 1 + 2 * 3\nforward a\ndown a * 2\nup -(a - 4)\nlet a = a + 1\nforward a",
+            no_includes
+        )
+    );
+    assert_eq!(
+        Err("Line 1: Undefined variable \"b\"".to_string()),
+        expand("forward b", no_includes)
+    );
+    assert_eq!(
+        Err("Line 2: Division by zero".to_string()),
+        expand("let a = 0\nforward 1 / a", no_includes)
+    );
+    assert_eq!(
+        Err("Line 1: Arithmetic overflow".to_string()),
+        expand("forward 2147483647 + 1", no_includes)
+    );
+    assert_eq!(
+        Err("Line 1: Invalid name \"forward\"".to_string()),
+        expand("let forward = 1", no_includes)
+    );
+}
+
+#[test]
+fn test_repeat() {
+    assert_eq!(
+        Command::parse_each(vec![
+            "down 1",
+            "forward 1",
+            "forward 1",
+            "down 2",
+            "forward 2",
+            "forward 2"
+        ]),
+        expand(
+            "let n = 1\nrepeat 2 {\n  down n\n  repeat 2 {\n    forward n\n  }\n  let n = n + 1\n}",
+            no_includes
+        )
+    );
+    assert_eq!(Ok(vec![]), expand("repeat 0 {\nforward 1\n}", no_includes));
+    assert_eq!(
+        Err("Line 1: Invalid repeat count -1".to_string()),
+        expand("repeat -1 {\nforward 1\n}", no_includes)
+    );
+    assert_eq!(
+        Err("Line 1: Block is never closed".to_string()),
+        expand("repeat 2 {\nrepeat 2 {\nforward 1\n}", no_includes)
+    );
+    assert_eq!(
+        Err("Line 2: Unexpected \"}\"".to_string()),
+        expand("forward 1\n}", no_includes)
+    );
+    assert_eq!(
+        Err("Line 1: Line 2: Script expands to more than 1000000 commands".to_string()),
+        expand("repeat 2000000000 {\nforward 1\n}", no_includes)
+    );
+    assert_eq!(
+        Err("Line 1: Script runs for more than 10000000 steps".to_string()),
+        expand("repeat 2000000000 {\n}", no_includes)
+    );
+    assert_eq!(
+        Err("Line 1: Line 2: Line 3: Script runs for more than 10000000 steps".to_string()),
+        expand(
+            "repeat 100000 {\nrepeat 100000 {\nlet a = 1\n}\n}",
+            no_includes
+        )
+    );
+}
+
+#[test]
+fn test_macros() {
+    assert_eq!(
+        Command::parse_each(vec!["down 2", "forward 4", "down 3", "forward 4"]),
+        expand(
+            "macro leg {\n  down d\n  forward 4\n}\nlet d = 2\nleg\nlet d = 3\nleg",
+            no_includes
+        )
+    );
+    assert_eq!(
+        Err("Line 1: Undefined macro \"leg\"".to_string()),
+        expand("leg", no_includes)
+    );
+    assert_eq!(
+        Err("Line 4: In macro \"a\": Line 2: \"a\" is expanded recursively".to_string()),
+        expand("macro a {\na\n}\na", no_includes)
+    );
+}
+
+#[test]
+fn test_include() {
+    let loader = |name: &str| match name {
+        "common.mis" => Ok("let step = 5\nmacro surface {\nup 10\n}".to_string()),
+        "loop.mis" => Ok("include \"loop.mis\"".to_string()),
+        "dir/self.mis" => Ok("include \"../dir/./self.mis\"".to_string()),
+        _ => match name
+            .strip_prefix("deep")
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            Some(n) => Ok(format!("include deep{}", n + 1)),
+            None => no_includes(name),
+        },
+    };
+    assert_eq!(
+        Command::parse_each(vec!["forward 5", "up 10"]),
+        expand("include \"common.mis\"\nforward step\nsurface", loader)
+    );
+    assert_eq!(
+        Err("Line 1: No such script \"missing.mis\"".to_string()),
+        expand("include missing.mis", loader)
+    );
+    assert_eq!(
+        Err("Line 1: In \"loop.mis\": Line 1: \"loop.mis\" is expanded recursively".to_string()),
+        expand("include loop.mis", loader)
+    );
+    assert_eq!(
+        Err(
+            "Line 1: In \"dir/self.mis\": Line 1: \"dir/self.mis\" is expanded recursively"
+                .to_string()
+        ),
+        expand("include ./dir/self.mis", loader)
+    );
+    let nested = expand("include deep0", loader).unwrap_err();
+    assert!(nested.starts_with("Line 1: In \"deep0\": Line 1: In \"deep1\""));
+    assert!(nested.ends_with("Includes nest more than 32 deep"));
+}
+
+#[test]
+fn test_expand_file() {
+    let dir = std::env::temp_dir().join(format!("advent-script-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.mis"), "include leg.mis\nrepeat 2 {\nleg\n}").unwrap();
+    fs::write(dir.join("leg.mis"), "macro leg {\nforward 5\n}").unwrap();
+    let result = expand_file(dir.join("main.mis"));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(Command::parse_each(vec!["forward 5", "forward 5"]), result);
+}
+
+#[test]
+fn test_nested_includes_are_relative() {
+    let dir = std::env::temp_dir().join(format!("advent-nested-{}", std::process::id()));
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("main.mis"), "include sub/a.mis\nforward 1").unwrap();
+    fs::write(dir.join("sub/a.mis"), "include b.mis").unwrap();
+    fs::write(dir.join("sub/b.mis"), "down 2").unwrap();
+    fs::write(dir.join("b.mis"), "up 2").unwrap();
+    let result = expand_file(dir.join("main.mis"));
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(Command::parse_each(vec!["down 2", "forward 1"]), result);
+}
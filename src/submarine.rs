@@ -1,38 +1,73 @@
-pub struct Submarine {
-    pub x: i32,
-    pub depth: i32,
-    pub aim: i32,
+use crate::observer::{self, MutationKind};
+use crate::scalar::{Overflow, Scalar};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Submarine<T = i32> {
+    pub x: T,
+    pub depth: T,
+    pub aim: T,
+    pub overflow: Overflow,
+    /// The fuel left, or `None` if the submarine doesn't track fuel.
+    pub fuel: Option<T>,
 }
 
-impl Submarine {
-    pub(crate) fn aim(&mut self, delta: i32) {
-        self.aim += delta;
+// Each change is reported to any `crate::observer::Observed` navigator that is acting.
+impl<T: Scalar> Submarine<T> {
+    pub(crate) fn aim(&mut self, delta: T) -> Result<(), String> {
+        let before = *self;
+        self.aim = self.overflow.add(self.aim, delta)?;
+        observer::record(MutationKind::AIM, delta, &before, self);
+        Ok(())
     }
 
-    pub(crate) fn dive(&mut self, delta: i32) {
-        self.depth += delta;
+    pub(crate) fn dive(&mut self, delta: T) -> Result<(), String> {
+        let before = *self;
+        self.depth = self.overflow.add(self.depth, delta)?;
+        observer::record(MutationKind::DIVE, delta, &before, self);
+        Ok(())
     }
 
-    pub(crate) fn ascend(&mut self, delta: i32) {
-        self.depth -= delta;
+    pub(crate) fn ascend(&mut self, delta: T) -> Result<(), String> {
+        let before = *self;
+        self.depth = self.overflow.sub(self.depth, delta)?;
+        observer::record(MutationKind::ASCEND, delta, &before, self);
+        Ok(())
     }
 
-    pub(crate) fn forward(&mut self, delta: i32) {
-        self.x += delta;
+    pub(crate) fn forward(&mut self, delta: T) -> Result<(), String> {
+        let before = *self;
+        self.x = self.overflow.add(self.x, delta)?;
+        observer::record(MutationKind::FORWARD, delta, &before, self);
+        Ok(())
     }
 
-    pub fn new() -> Submarine {
+    pub fn with_overflow(overflow: Overflow) -> Submarine<T> {
+        Submarine {
+            x: T::default(),
+            depth: T::default(),
+            aim: T::default(),
+            overflow,
+            fuel: None,
+        }
+    }
+
+    pub fn with_fuel(fuel: T) -> Submarine<T> {
         Submarine {
-            x: 0,
-            depth: 0,
-            aim: 0,
+            fuel: Some(fuel),
+            ..Submarine::default()
         }
     }
 }
 
-impl Default for Submarine {
+impl Submarine {
+    pub fn new() -> Submarine {
+        Submarine::default()
+    }
+}
+
+impl<T: Scalar> Default for Submarine<T> {
     fn default() -> Self {
-        Submarine::new()
+        Submarine::with_overflow(Overflow::default())
     }
 }
 
@@ -42,15 +77,15 @@ fn test_dive() {
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 0);
 
-    sub.dive(1);
+    sub.dive(1).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 1);
 
-    sub.dive(2);
+    sub.dive(2).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 3);
 
-    sub.dive(-3);
+    sub.dive(-3).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 0);
 }
@@ -61,15 +96,15 @@ fn test_ascend() {
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 0);
 
-    sub.ascend(1);
+    sub.ascend(1).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, -1);
 
-    sub.ascend(2);
+    sub.ascend(2).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, -3);
 
-    sub.ascend(-3);
+    sub.ascend(-3).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 0);
 }
@@ -80,15 +115,15 @@ fn test_forward() {
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 0);
 
-    sub.forward(1);
+    sub.forward(1).unwrap();
     assert_eq!(sub.x, 1);
     assert_eq!(sub.depth, 0);
 
-    sub.forward(2);
+    sub.forward(2).unwrap();
     assert_eq!(sub.x, 3);
     assert_eq!(sub.depth, 0);
 
-    sub.forward(-3);
+    sub.forward(-3).unwrap();
     assert_eq!(sub.x, 0);
     assert_eq!(sub.depth, 0);
 }
@@ -98,12 +133,38 @@ fn test_aim() {
     let mut sub = Submarine::new();
     assert_eq!(sub.aim, 0);
 
-    sub.aim(1);
+    sub.aim(1).unwrap();
     assert_eq!(sub.aim, 1);
 
-    sub.aim(2);
+    sub.aim(2).unwrap();
     assert_eq!(sub.aim, 3);
 
-    sub.aim(-3);
+    sub.aim(-3).unwrap();
     assert_eq!(sub.aim, 0);
 }
+
+#[test]
+fn test_overflow() {
+    let mut sub = Submarine::new();
+    sub.dive(i32::MAX).unwrap();
+    assert_eq!(
+        Err("Arithmetic overflow: 2147483647 + 1".to_string()),
+        sub.dive(1)
+    );
+    assert_eq!(sub.depth, i32::MAX);
+
+    let mut sub = Submarine::with_overflow(Overflow::SATURATING);
+    sub.dive(i32::MAX).unwrap();
+    sub.dive(1).unwrap();
+    assert_eq!(sub.depth, i32::MAX);
+
+    let mut sub = Submarine::with_overflow(Overflow::WRAPPING);
+    sub.dive(i32::MAX).unwrap();
+    sub.dive(1).unwrap();
+    assert_eq!(sub.depth, i32::MIN);
+
+    let mut sub = Submarine::<i64>::default();
+    sub.dive(i32::MAX.into()).unwrap();
+    sub.dive(1).unwrap();
+    assert_eq!(sub.depth, i64::from(i32::MAX) + 1);
+}
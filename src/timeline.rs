@@ -0,0 +1,365 @@
+use crate::command::Command;
+use crate::navigator::Navigator;
+use crate::submarine::Submarine;
+use std::fmt;
+use std::str::FromStr;
+
+/// A command issued at `time` seconds into the mission, written as `12.5s forward 5`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedCommand {
+    pub time: f64,
+    pub command: Command,
+}
+
+impl TimedCommand {
+    pub fn new(time: f64, command: Command) -> TimedCommand {
+        TimedCommand { time, command }
+    }
+
+    /// Parses one command per line. Times must not go backwards. Lines are numbered from 1.
+    pub fn parse_each<'a>(
+        lines: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<TimedCommand>, String> {
+        let mut commands: Vec<TimedCommand> = vec![];
+        for (lineno, line) in lines.into_iter().enumerate() {
+            let command = line
+                .parse::<TimedCommand>()
+                .map_err(|e| format!("Line {}: {}", lineno + 1, e))?;
+            if let Some(previous) = commands.last() {
+                if command.time < previous.time {
+                    return Err(format!(
+                        "Line {}: {}s is before the previous command at {}s",
+                        lineno + 1,
+                        command.time,
+                        previous.time
+                    ));
+                }
+            }
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+}
+
+impl FromStr for TimedCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<TimedCommand, String> {
+        let (time_str, command_str) = line
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("Invalid timed command: \"{}\"", line))?;
+        let time = time_str
+            .strip_suffix('s')
+            .and_then(|t| t.parse::<f64>().ok())
+            .filter(|t| t.is_finite() && *t >= 0.0)
+            .ok_or_else(|| format!("Invalid timestamp: \"{}\"", time_str))?;
+        Ok(TimedCommand::new(time, command_str.parse()?))
+    }
+}
+
+impl fmt::Display for TimedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}s {}", self.time, self.command)
+    }
+}
+
+/// How hard the submarine can be driven along each axis, in units per second and units per second
+/// squared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    pub max_speed: f64,
+    pub max_acceleration: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sample {
+    pub time: f64,
+    pub x: f64,
+    pub depth: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Axis {
+    position: f64,
+    velocity: f64,
+}
+
+impl Axis {
+    // Accelerates toward the target as hard as allowed while still being able to stop on it, never
+    // changing velocity by more than the acceleration limit allows in a tick.
+    fn step(&mut self, target: f64, limits: &Limits, dt: f64) {
+        let error = target - self.position;
+        let max_change = limits.max_acceleration * dt;
+        // The fastest speed from which shedding `max_change` a tick still stops within `error`.
+        let stopping_speed = limits.max_acceleration
+            * ((dt * dt / 4.0 + 2.0 * error.abs() / limits.max_acceleration).sqrt() - dt / 2.0);
+        let desired = if error == 0.0 {
+            0.0
+        } else {
+            error.signum() * stopping_speed.min(limits.max_speed)
+        };
+        let initial = self.velocity;
+        self.velocity += (desired - self.velocity).clamp(-max_change, max_change);
+
+        let travel = self.velocity * dt;
+        if error != 0.0
+            && travel.signum() == error.signum()
+            && travel.abs() >= error.abs()
+            && initial.abs() <= max_change
+        {
+            // Arrive rather than overshoot by a fraction of a tick, which is only possible when
+            // the submarine is slow enough to stop within one.
+            self.position = target;
+            self.velocity = 0.0;
+        } else {
+            self.position += travel;
+        }
+    }
+}
+
+/// The most samples a single run may produce, which bounds the memory a long duration can ask for.
+const MAX_SAMPLES: f64 = 10_000_000.0;
+
+/// Integrates the submarine's motion as it chases the positions commanded through a navigator.
+pub struct Simulator<N> {
+    navigator: N,
+    limits: Limits,
+    tick: f64,
+}
+
+impl<N: Navigator> Simulator<N> {
+    /// `tick` is the integration step in seconds.
+    pub fn new(navigator: N, limits: Limits, tick: f64) -> Result<Simulator<N>, String> {
+        if !(tick > 0.0 && tick.is_finite()) {
+            return Err(format!("Invalid tick {}", tick));
+        }
+        if !(limits.max_speed > 0.0 && limits.max_acceleration > 0.0) {
+            return Err(format!("Invalid limits {:?}", limits));
+        }
+        Ok(Simulator {
+            navigator,
+            limits,
+            tick,
+        })
+    }
+
+    /// Simulates the first `duration` seconds of a mission. `commands` must be in time order.
+    pub fn run(&self, commands: &[TimedCommand], duration: f64) -> Result<Trajectory, String> {
+        if !(duration >= 0.0 && duration.is_finite()) {
+            return Err(format!("Invalid duration {}", duration));
+        }
+        let mut ticks = (duration / self.tick).ceil();
+        if ticks > 0.0 && (ticks - 1.0) * self.tick >= duration {
+            ticks -= 1.0;
+        }
+        if ticks >= MAX_SAMPLES {
+            return Err(format!(
+                "A duration of {}s is too long for a tick of {}s",
+                duration, self.tick
+            ));
+        }
+        let ticks = ticks as usize;
+        let mut target = Submarine::new();
+        let mut pending = commands.iter().enumerate().peekable();
+        let (mut x, mut depth) = (Axis::default(), Axis::default());
+        let mut samples = Vec::with_capacity(ticks + 1);
+
+        for i in 0..=ticks {
+            // Every tick is a whole tick long except the last, which stops at `duration`.
+            let time = if i == ticks {
+                duration
+            } else {
+                i as f64 * self.tick
+            };
+            while let Some((idx, timed)) = pending.next_if(|(_, c)| c.time <= time) {
+                self.navigator
+                    .act_on(&mut target, &timed.command)
+                    .map_err(|e| format!("Command {}: {}", idx, e))?;
+            }
+            samples.push(Sample {
+                time,
+                x: x.position,
+                depth: depth.position,
+            });
+            if i < ticks {
+                let dt = if i + 1 == ticks {
+                    duration - time
+                } else {
+                    self.tick
+                };
+                x.step(f64::from(target.x), &self.limits, dt);
+                depth.step(f64::from(target.depth), &self.limits, dt);
+            }
+        }
+        Ok(Trajectory {
+            tick: self.tick,
+            samples,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Trajectory {
+    tick: f64,
+    samples: Vec<Sample>,
+}
+
+impl Trajectory {
+    /// One sample per tick, starting at time zero and ending at the end of the simulated period.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Where the submarine was at `time`, interpolating between ticks. `None` if `time` is outside
+    /// the simulated period or isn't a number.
+    pub fn at(&self, time: f64) -> Option<Sample> {
+        if !(time >= 0.0 && time <= self.samples.last()?.time) {
+            return None;
+        }
+        let idx = (time / self.tick).floor() as usize;
+        let before = self.samples.get(idx)?;
+        let Some(after) = self.samples.get(idx + 1) else {
+            return (time == before.time).then_some(*before);
+        };
+        let t = (time - before.time) / (after.time - before.time);
+        Some(Sample {
+            time,
+            x: before.x + (after.x - before.x) * t,
+            depth: before.depth + (after.depth - before.depth) * t,
+        })
+    }
+}
+
+#[cfg(test)]
+use crate::navigator::AimingNav;
+
+#[cfg(test)]
+const LIMITS: Limits = Limits {
+    max_speed: 2.0,
+    max_acceleration: 1.0,
+};
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        Ok(TimedCommand::new(12.5, "forward 5".parse().unwrap())),
+        "12.5s forward 5".parse::<TimedCommand>()
+    );
+    for line in ["0s up 3", "12.5s forward 5", "3600s down -1"] {
+        assert_eq!(line, line.parse::<TimedCommand>().unwrap().to_string());
+    }
+    assert_eq!(
+        Err("Invalid timestamp: \"12\"".to_string()),
+        "12 forward 5".parse::<TimedCommand>()
+    );
+    assert_eq!(
+        Err("Invalid timestamp: \"-1s\"".to_string()),
+        "-1s forward 5".parse::<TimedCommand>()
+    );
+    assert_eq!(
+        Err("Line 2: 1s is before the previous command at 2s".to_string()),
+        TimedCommand::parse_each(vec!["2s forward 1", "1s forward 1"])
+    );
+}
+
+#[test]
+fn test_motion_respects_limits() {
+    let commands = TimedCommand::parse_each(vec!["0s forward 10"]).unwrap();
+    let simulator = Simulator::new(AimingNav::new(), LIMITS, 0.1).unwrap();
+    let trajectory = simulator.run(&commands, 20.0).unwrap();
+
+    let samples = trajectory.samples();
+    assert_eq!(201, samples.len());
+    let mut previous_speed = 0.0;
+    for pair in samples.windows(2) {
+        let speed = (pair[1].x - pair[0].x) / 0.1;
+        assert!(speed <= LIMITS.max_speed + 1e-9, "{}", speed);
+        assert!(speed >= 0.0);
+        previous_speed = speed;
+    }
+    assert_eq!(0.0, previous_speed);
+    assert_eq!(10.0, samples.last().unwrap().x);
+
+    // Accelerating at 1 unit/s^2 covers half a unit in the first second.
+    assert!((trajectory.at(1.0).unwrap().x - 0.5).abs() < 0.1);
+    // Cruising at 2 units/s after the first two seconds.
+    assert!((trajectory.at(4.0).unwrap().x - 6.0).abs() < 0.2);
+}
+
+#[test]
+fn test_position_at_time() {
+    let commands = TimedCommand::parse_each(vec![
+        "0s down 1",
+        "5s forward 4",
+        "30s up 1",
+        "30s forward 2",
+    ])
+    .unwrap();
+    let simulator = Simulator::new(AimingNav::new(), LIMITS, 0.5).unwrap();
+    let trajectory = simulator.run(&commands, 60.0).unwrap();
+
+    // Nothing moves until the first forward.
+    let early = trajectory.at(4.75).unwrap();
+    assert_eq!((0.0, 0.0), (early.x, early.depth));
+    let at_30 = trajectory.at(30.0).unwrap();
+    assert_eq!((4.0, 4.0), (at_30.x, at_30.depth));
+    let end = trajectory.at(60.0).unwrap();
+    assert_eq!((6.0, 4.0), (end.x, end.depth));
+    assert_eq!(None, trajectory.at(60.25));
+
+    let halfway = trajectory.at(30.25).unwrap();
+    assert_eq!(30.25, halfway.time);
+    assert_eq!(None, trajectory.at(f64::NAN));
+    assert_eq!(None, trajectory.at(f64::INFINITY));
+
+    // The last tick is cut short rather than running past the end.
+    let trajectory = simulator.run(&commands, 5.75).unwrap();
+    let times: Vec<f64> = trajectory.samples().iter().map(|s| s.time).collect();
+    assert_eq!(13, times.len());
+    assert_eq!([5.0, 5.5, 5.75], times[10..]);
+    assert_eq!(None, trajectory.at(5.8));
+    assert_eq!(5.6, trajectory.at(5.6).unwrap().time);
+}
+
+#[test]
+fn test_arrival_respects_acceleration() {
+    // Too fast to stop on the target within a tick, so it overshoots and comes back.
+    let mut axis = Axis {
+        position: 9.9,
+        velocity: 2.0,
+    };
+    for _ in 0..200 {
+        let before = axis.velocity;
+        axis.step(10.0, &LIMITS, 0.1);
+        assert!((axis.velocity - before).abs() <= 0.1 + 1e-9, "{:?}", axis);
+    }
+    assert_eq!((10.0, 0.0), (axis.position, axis.velocity));
+}
+
+#[test]
+fn test_invalid_simulator() {
+    assert!(Simulator::new(AimingNav::new(), LIMITS, 0.0).is_err());
+    assert!(Simulator::new(
+        AimingNav::new(),
+        Limits {
+            max_speed: 0.0,
+            max_acceleration: 1.0
+        },
+        0.1
+    )
+    .is_err());
+
+    let simulator = Simulator::new(AimingNav::new(), LIMITS, 0.1).unwrap();
+    for duration in [f64::INFINITY, f64::NAN, -1.0] {
+        assert_eq!(
+            Some(format!("Invalid duration {}", duration)),
+            simulator.run(&[], duration).err()
+        );
+    }
+    assert_eq!(
+        Some("A duration of 1000000000s is too long for a tick of 0.1s".to_string()),
+        simulator.run(&[], 1e9).err()
+    );
+    assert_eq!(1, simulator.run(&[], 0.0).unwrap().samples().len());
+}
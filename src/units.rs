@@ -0,0 +1,119 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Units that command values can be given in. Submarines work in metres.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Unit {
+    #[default]
+    METRE,
+    FOOT,
+    FATHOM,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 3] = [Unit::METRE, Unit::FOOT, Unit::FATHOM];
+
+    // The length of the unit in tenths of a millimetre, which is exact for all of them.
+    pub(crate) fn length(self) -> i64 {
+        match self {
+            Unit::METRE => 10_000,
+            Unit::FOOT => 3_048,
+            Unit::FATHOM => 18_288,
+        }
+    }
+
+    /// Converts `value` of this unit to whole metres. Lengths that aren't a whole number of metres
+    /// are rejected rather than rounded, since logs in whole metres can't hold them; parse them as
+    /// `f64` or `crate::fixed::Fixed` commands instead.
+    pub fn to_metres(self, value: i32) -> Result<i32, String> {
+        let scaled = i64::from(value) * self.length();
+        if scaled % 10_000 != 0 {
+            return Err(format!("{}{} is not a whole number of metres", value, self));
+        }
+        i32::try_from(scaled / 10_000).map_err(|_| format!("{}{} is out of range", value, self))
+    }
+
+    pub fn to_metres_f64(self, value: f64) -> f64 {
+        value * self.length() as f64 / 10_000.0
+    }
+
+    pub fn from_metres(self, metres: f64) -> f64 {
+        metres * 10_000.0 / self.length() as f64
+    }
+
+    /// Shows a length in metres in this unit, to at most two decimal places.
+    pub fn format(self, metres: f64) -> String {
+        let value = format!("{:.2}", self.from_metres(metres));
+        let value = value.trim_end_matches('0').trim_end_matches('.');
+        // Avoid "-0" for tiny negative lengths.
+        let value = if value == "-0" { "0" } else { value };
+        format!("{}{}", value, self)
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Unit, String> {
+        match name {
+            "m" => Ok(Unit::METRE),
+            "ft" => Ok(Unit::FOOT),
+            "fathom" => Ok(Unit::FATHOM),
+            _ => Err(format!(
+                "Unknown unit \"{}\", expected one of m, ft or fathom",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Unit::METRE => "m",
+            Unit::FOOT => "ft",
+            Unit::FATHOM => "fathom",
+        })
+    }
+}
+
+#[test]
+fn test_to_metres() {
+    assert_eq!(Ok(10), Unit::METRE.to_metres(10));
+    // 1250ft is exactly 381m, and 625 fathoms exactly 1143m.
+    assert_eq!(Ok(381), Unit::FOOT.to_metres(1250));
+    assert_eq!(Ok(-381), Unit::FOOT.to_metres(-1250));
+    assert_eq!(Ok(2286), Unit::FATHOM.to_metres(1250));
+    assert_eq!(Ok(3048), Unit::FOOT.to_metres(10_000));
+    // 6ft is 1.8288m and 1ft is 0.3048m.
+    for (unit, value) in [(Unit::FOOT, 6), (Unit::FOOT, 1), (Unit::FATHOM, 2)] {
+        assert_eq!(
+            Err(format!("{}{} is not a whole number of metres", value, unit)),
+            unit.to_metres(value)
+        );
+    }
+    assert_eq!(
+        Err("2000000000fathom is out of range".to_string()),
+        Unit::FATHOM.to_metres(2_000_000_000)
+    );
+}
+
+#[test]
+fn test_format() {
+    assert_eq!("15m", Unit::METRE.format(15.0));
+    assert_eq!("49.21ft", Unit::FOOT.format(15.0));
+    assert_eq!("8.2fathom", Unit::FATHOM.format(14.99616));
+    assert_eq!("-10ft", Unit::FOOT.format(-3.048));
+    assert_eq!("0ft", Unit::FOOT.format(-0.001));
+}
+
+#[test]
+fn test_names() {
+    for unit in Unit::ALL {
+        assert_eq!(Ok(unit), unit.to_string().parse());
+    }
+    assert_eq!(
+        Err("Unknown unit \"yd\", expected one of m, ft or fathom".to_string()),
+        "yd".parse::<Unit>()
+    );
+}
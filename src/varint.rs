@@ -0,0 +1,74 @@
+// LEB128 variable length integers. Signed values are zigzag encoded first so that small negative
+// numbers stay small.
+
+pub fn write_unsigned(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+pub fn write_signed(out: &mut Vec<u8>, value: i64) {
+    write_unsigned(out, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+/// Reads an unsigned varint from the start of `bytes`, returning the value and the number of bytes
+/// consumed.
+pub fn read_unsigned(bytes: &[u8]) -> Result<(u64, usize), String> {
+    let mut value = 0u64;
+    for (idx, byte) in bytes.iter().enumerate() {
+        let shift = 7 * idx as u32;
+        let bits = u64::from(byte & 0x7f);
+        if shift >= 64 || (bits << shift) >> shift != bits {
+            return Err("Varint is too long".to_string());
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value, idx + 1));
+        }
+    }
+    Err("Truncated varint".to_string())
+}
+
+pub fn read_signed(bytes: &[u8]) -> Result<(i64, usize), String> {
+    let (value, len) = read_unsigned(bytes)?;
+    Ok((((value >> 1) as i64) ^ -((value & 1) as i64), len))
+}
+
+#[test]
+fn test_round_trip() {
+    for value in [0, 1, -1, 63, -64, 64, 300, -300, i64::MAX, i64::MIN] {
+        let mut out = vec![];
+        write_signed(&mut out, value);
+        assert_eq!(Ok((value, out.len())), read_signed(&out));
+    }
+    for value in [0, 127, 128, u64::MAX] {
+        let mut out = vec![];
+        write_unsigned(&mut out, value);
+        assert_eq!(Ok((value, out.len())), read_unsigned(&out));
+    }
+}
+
+#[test]
+fn test_encoding() {
+    let mut out = vec![];
+    write_signed(&mut out, -1);
+    write_signed(&mut out, 1);
+    write_unsigned(&mut out, 300);
+    assert_eq!(vec![0x01, 0x02, 0xac, 0x02], out);
+}
+
+#[test]
+fn test_invalid() {
+    assert_eq!(Err("Truncated varint".to_string()), read_unsigned(&[]));
+    assert_eq!(Err("Truncated varint".to_string()), read_unsigned(&[0x80]));
+    assert_eq!(
+        Err("Varint is too long".to_string()),
+        read_unsigned(&[0xff; 11])
+    );
+}